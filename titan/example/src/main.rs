@@ -1,5 +1,5 @@
 use titan::ResultIteration;
-use titan::ECS;
+use titan::{Entity, With, ECS};
 use titan_macros::component;
 
 fn main() {
@@ -38,20 +38,28 @@ fn main() {
     ecs.spawn_bundle((Position { x: 103.4, y: -71.7 }, Id(55)));
 
     // Query for specific archetypes. & asks for read permission, whereas &mut requests write
-    // permission. Note, once again the order does not matter.
-    for (id, person, position) in ecs
-        .query::<(&Id, &mut Person, &mut Position)>()
+    // permission. Entity yields the handle of each entity. Note, once again the order does not
+    // matter.
+    for (entity, id, person, mut position) in ecs
+        .query::<(Entity, &Id, &mut Person, &mut Position)>()
         .result_iter()
     {
         println!(
-            "{:?} is at {:?} and has a height of {}",
-            id, position, person.height
+            "{:?} with {:?} is at {:?} and has a height of {}",
+            entity, id, position, person.height
         );
 
-        // We can also mutate components which were requested with &mut.
+        // We can also mutate components which were requested with &mut. They are yielded as Mut,
+        // which records the component as changed once it is written to.
         position.x = 0.0;
     }
 
+    // Filters such as With and Without narrow down a query without yielding anything, so only the
+    // Id of entities which also have a Person is yielded here.
+    for id in ecs.query_filtered::<(&Id,), (With<Person>,)>().result_iter() {
+        println!("{:?} belongs to a person", id);
+    }
+
     // Serialize the component storage into JSON.
     let ecs_serial = ecs.serialize();
 
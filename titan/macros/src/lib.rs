@@ -1,29 +1,138 @@
 use proc_macro::TokenStream;
+use proc_macro2::Span;
 use quote::quote;
-use syn::{parse, ItemStruct};
+use syn::{
+    parse, parse_macro_input, AttributeArgs, Index, ItemStruct, Lit, Member, Meta, NestedMeta,
+};
+
+///
+/// Options given to the `component` attribute, such as `#[component(map_entities)]` or
+/// `#[component(name = "game::Position")]`.
+///
+struct ComponentOptions {
+    map_entities: bool,
+    transient: bool,
+    name: Option<String>,
+}
+impl ComponentOptions {
+    fn parse(attribute_args: AttributeArgs) -> Self {
+        let mut options = ComponentOptions {
+            map_entities: false,
+            transient: false,
+            name: None,
+        };
+        for nested_meta in attribute_args {
+            match nested_meta {
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("map_entities") => {
+                    options.map_entities = true
+                }
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("transient") => {
+                    options.transient = true
+                }
+                NestedMeta::Meta(Meta::NameValue(name_value))
+                    if name_value.path.is_ident("name") =>
+                {
+                    match name_value.lit {
+                        Lit::Str(name) => options.name = Some(name.value()),
+                        _ => panic!("Component name must be a string literal"),
+                    }
+                }
+                _ => panic!("Unknown component option"),
+            }
+        }
+        options
+    }
+}
 
 #[proc_macro_attribute]
-pub fn component(_metadata: TokenStream, input: TokenStream) -> TokenStream {
-    let item_struct =
+pub fn component(metadata: TokenStream, input: TokenStream) -> TokenStream {
+    let options = ComponentOptions::parse(parse_macro_input!(metadata as AttributeArgs));
+    let mut item_struct =
         parse::<ItemStruct>(TokenStream::from(input)).expect("Could not parse item struct");
     let item_struct_name = item_struct.ident.clone();
-    let item_struct_name_string = item_struct_name.to_string();
+    let component_kind_name = options
+        .name
+        .clone()
+        .unwrap_or_else(|| item_struct_name.to_string());
+
+    // Collect and strip the `#[entity]` attributes of fields holding entity references
+    let mut entity_members = Vec::new();
+    for (i, field) in item_struct.fields.iter_mut().enumerate() {
+        let attribute_count = field.attrs.len();
+        field
+            .attrs
+            .retain(|attribute| !attribute.path.is_ident("entity"));
+        if field.attrs.len() != attribute_count {
+            entity_members.push(match &field.ident {
+                Some(ident) => Member::Named(ident.clone()),
+                None => Member::Unnamed(Index {
+                    index: i as u32,
+                    span: Span::call_site(),
+                }),
+            });
+        }
+    }
+
+    let map_entities_impl = if entity_members.is_empty() {
+        quote! {}
+    } else {
+        quote! {
+            impl titan::MapEntities for #item_struct_name {
+                fn map_entities(
+                    &mut self,
+                    entity_map: &std::collections::HashMap<titan::Entity, titan::Entity>,
+                ) {
+                    #(titan::MapEntities::map_entities(&mut self.#entity_members, entity_map);)*
+                }
+            }
+        }
+    };
+    let map_entities_fn = if options.map_entities || !entity_members.is_empty() {
+        quote! {
+            fn get_map_entities_fn() -> Option<titan::MapEntitiesFn<Self>> {
+                Some(<Self as titan::MapEntities>::map_entities)
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    // Transient components are never saved and so need not be serializable
+    let (derive, transient_fn) = if options.transient {
+        (
+            quote! { #[derive(Debug)] },
+            quote! {
+                fn is_transient() -> bool {
+                    true
+                }
+            },
+        )
+    } else {
+        (
+            quote! { #[derive(Debug, serde::Serialize, serde::Deserialize)] },
+            quote! {},
+        )
+    };
+
     let expanded = quote! {
-        #[derive(Debug, serde::Serialize, serde::Deserialize)]
+        #derive
         #item_struct
+        #map_entities_impl
         impl titan::ComponentMeta for #item_struct_name {
             fn get_component_kind() -> titan::ComponentKind {
-                titan::ComponentKind(String::from(#item_struct_name_string))
+                titan::ComponentKind(String::from(#component_kind_name))
             }
+            #map_entities_fn
+            #transient_fn
         }
         impl titan::ComponentMeta for &#item_struct_name {
             fn get_component_kind() -> titan::ComponentKind {
-                titan::ComponentKind(String::from(#item_struct_name_string))
+                titan::ComponentKind(String::from(#component_kind_name))
             }
         }
         impl titan::ComponentMeta for &mut #item_struct_name {
             fn get_component_kind() -> titan::ComponentKind {
-                titan::ComponentKind(String::from(#item_struct_name_string))
+                titan::ComponentKind(String::from(#component_kind_name))
             }
         }
     };
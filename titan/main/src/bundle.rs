@@ -1,5 +1,4 @@
-use crate::{storage::Archetype, ComponentKind, ComponentMeta, EntityId};
-use paste::paste;
+use crate::{entity::Entity, storage::Archetype, ComponentKind, ComponentMeta, TitanError};
 use std::hash::Hash;
 
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
@@ -17,8 +16,20 @@ impl BundleKind {
 }
 
 pub trait Bundle {
-    fn push_into_archetype(self, entity_id: EntityId, archetype: &mut Archetype);
-    fn get_bundle_kind() -> BundleKind;
+    fn push_into_archetype(self, entity: Entity, archetype: &mut Archetype);
+    fn get_component_kinds() -> Vec<ComponentKind>;
+    ///
+    /// Get the kinds of the components in the bundle which are never saved.
+    ///
+    fn get_transient_component_kinds() -> Vec<ComponentKind>;
+    ///
+    /// Check that the component vecs of the archetype hold the component types of the bundle.
+    /// Returns an error if a component kind is used by a different type in the archetype.
+    ///
+    fn check_component_types(archetype: &Archetype) -> Result<(), TitanError>;
+    fn get_bundle_kind() -> BundleKind {
+        BundleKind::from_component_kinds(&Self::get_component_kinds())
+    }
 }
 
 macro_rules! bundle_impl {
@@ -28,15 +39,25 @@ macro_rules! bundle_impl {
         impl<$($name),*> Bundle for ($($name),*,)
         where
             $($name: 'static + ComponentMeta),* {
-            fn push_into_archetype(self, entity_id: EntityId, archetype: &mut Archetype) {
-                archetype.push_entity_id(entity_id);
+            fn push_into_archetype(self, entity: Entity, archetype: &mut Archetype) {
+                archetype.push_entity_id(entity);
                 $(archetype.push_component(self.$i));*
             }
-            #[allow(non_snake_case)]
-            fn get_bundle_kind() -> BundleKind {
-                $(let paste!{[<kind_ $name>]} = <$name>::get_component_kind();)*
-                let bundle_kind = BundleKind::from_component_kinds(&[$(paste!{[<kind_ $name>]}),*]);
-                bundle_kind
+            fn get_component_kinds() -> Vec<ComponentKind> {
+                vec![$(<$name>::get_component_kind()),*]
+            }
+            fn get_transient_component_kinds() -> Vec<ComponentKind> {
+                let mut component_kinds = Vec::new();
+                $(
+                    if <$name>::is_transient() {
+                        component_kinds.push(<$name>::get_component_kind());
+                    }
+                )*
+                component_kinds
+            }
+            fn check_component_types(archetype: &Archetype) -> Result<(), TitanError> {
+                $(archetype.check_component_type::<$name>()?;)*
+                Ok(())
             }
         }
     };
@@ -0,0 +1,371 @@
+use crate::{bundle::BundleKind, EntityId, TitanError};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, mem};
+
+///
+/// Handle to a spawned entity. The generation is incremented each time an index is reused, so a
+/// handle to a despawned entity never aliases the entity which later takes its index.
+///
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Serialize, Deserialize)]
+pub struct Entity {
+    index: EntityId,
+    generation: u32,
+}
+impl Entity {
+    pub fn index(&self) -> EntityId {
+        self.index
+    }
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+}
+
+///
+/// Remaps the entity references held by a component when entities are loaded under new handles.
+///
+/// Derived by the `component` attribute macro for components with fields marked `#[entity]`. To
+/// implement it manually instead, mark the component with `#[component(map_entities)]`.
+///
+pub trait MapEntities {
+    fn map_entities(&mut self, entity_map: &HashMap<Entity, Entity>);
+}
+impl MapEntities for Entity {
+    fn map_entities(&mut self, entity_map: &HashMap<Entity, Entity>) {
+        // Entities which were not loaded keep their handle
+        if let Some(entity) = entity_map.get(self) {
+            *self = *entity;
+        }
+    }
+}
+impl<T: MapEntities> MapEntities for Option<T> {
+    fn map_entities(&mut self, entity_map: &HashMap<Entity, Entity>) {
+        if let Some(value) = self {
+            value.map_entities(entity_map);
+        }
+    }
+}
+impl<T: MapEntities> MapEntities for Vec<T> {
+    fn map_entities(&mut self, entity_map: &HashMap<Entity, Entity>) {
+        for value in self.iter_mut() {
+            value.map_entities(entity_map);
+        }
+    }
+}
+
+///
+/// Remaps the entity references of a component of type `T`, see `ComponentMeta`.
+///
+pub type MapEntitiesFn<T> = fn(&mut T, &HashMap<Entity, Entity>);
+
+///
+/// How loading entities into an existing world handles a loaded entity whose index is already in
+/// use by a living entity.
+///
+/// Regardless of the policy, a loaded entity whose handle may already have been handed out, such
+/// as the handle of a despawned entity, is given its index under a new generation, so stale
+/// handles never refer to loaded entities.
+///
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum EntityCollisionPolicy {
+    ///
+    /// Give the loaded entity a fresh handle.
+    ///
+    Remap,
+    ///
+    /// Despawn the living entity and give its index to the loaded entity.
+    ///
+    Overwrite,
+    ///
+    /// Fail the load, leaving the world untouched.
+    ///
+    Error,
+}
+
+///
+/// Position of an entity's row within the storage.
+///
+#[derive(Debug, Clone)]
+pub(crate) struct EntityLocation {
+    pub(crate) bundle_kind: BundleKind,
+    pub(crate) row: usize,
+}
+
+///
+/// Entities of saves without entity slots may take indices up to this bound regardless of how
+/// many entities are loaded, see `Entities::get_restored_index_bound`.
+///
+const MIN_RESTORED_INDEX_BOUND: usize = 1 << 20;
+///
+/// Entities of saves without entity slots may take indices up to this many times the number of
+/// loaded entities.
+///
+const RESTORED_INDEX_BOUND_FACTOR: usize = 16;
+
+///
+/// Saved state of the entity allocator: the generation of every index, which is the generation
+/// of the saved entity or the next generation to hand out, and the free list.
+///
+#[derive(Serialize, Deserialize)]
+pub(crate) struct EntitySlots {
+    generations: Vec<u32>,
+    free_indices: Vec<EntityId>,
+}
+impl EntitySlots {
+    ///
+    /// Record the living entity as despawned, used for entities which are not saved so their
+    /// handles stay stale after loading.
+    ///
+    pub(crate) fn free(&mut self, entity: Entity) {
+        self.generations[entity.index] = entity.generation.wrapping_add(1);
+        self.free_indices.push(entity.index);
+    }
+}
+
+struct EntitySlot {
+    generation: u32,
+    alive: bool,
+    location: Option<EntityLocation>,
+}
+
+///
+/// Entity allocator. Indices of despawned entities are kept in a free list and handed out again
+/// with an incremented generation.
+///
+pub(crate) struct Entities {
+    slots: Vec<EntitySlot>,
+    free_indices: Vec<EntityId>,
+    alive_count: usize,
+    ///
+    /// Whether the slots have been restored from a save, which then bound the indices restored
+    /// entities may take.
+    ///
+    has_restored_slots: bool,
+}
+impl Entities {
+    pub(crate) fn new() -> Self {
+        Self {
+            slots: Vec::new(),
+            free_indices: Vec::new(),
+            alive_count: 0,
+            has_restored_slots: false,
+        }
+    }
+    pub(crate) fn allocate(&mut self) -> Entity {
+        // Free list may contain indices which have since been reserved, skip those
+        while let Some(index) = self.free_indices.pop() {
+            let slot = &mut self.slots[index];
+            if !slot.alive {
+                slot.alive = true;
+                self.alive_count += 1;
+                return Entity {
+                    index,
+                    generation: slot.generation,
+                };
+            }
+        }
+        self.slots.push(EntitySlot {
+            generation: 0,
+            alive: true,
+            location: None,
+        });
+        self.alive_count += 1;
+        Entity {
+            index: self.slots.len() - 1,
+            generation: 0,
+        }
+    }
+    ///
+    /// Get the generation of every index and the free list, with each free index listed once.
+    ///
+    pub(crate) fn get_slots(&self) -> EntitySlots {
+        let mut is_listed = vec![false; self.slots.len()];
+        let mut free_indices: Vec<EntityId> = self
+            .free_indices
+            .iter()
+            .rev()
+            .filter(|index| {
+                !self.slots[**index].alive && !mem::replace(&mut is_listed[**index], true)
+            })
+            .copied()
+            .collect();
+        free_indices.reverse();
+        EntitySlots {
+            generations: self.slots.iter().map(|slot| slot.generation).collect(),
+            free_indices,
+        }
+    }
+    ///
+    /// Restore saved slots as free indices, before any entity has been restored, so handles
+    /// which were stale when saving stay stale.
+    ///
+    /// Returns an error if an entity has already been restored or a free index is out of bounds.
+    ///
+    pub(crate) fn restore_slots(&mut self, entity_slots: EntitySlots) -> Result<(), TitanError> {
+        if !self.slots.is_empty() {
+            return Err(TitanError::malformed(
+                "Entity slots must precede the entities of a save",
+            ));
+        }
+        let slot_count = entity_slots.generations.len();
+        if let Some(index) = entity_slots
+            .free_indices
+            .iter()
+            .find(|index| **index >= slot_count)
+        {
+            return Err(TitanError::MalformedSaveData(format!(
+                "Free entity index {} is out of bounds",
+                index
+            )));
+        }
+        self.slots = entity_slots
+            .generations
+            .into_iter()
+            .map(|generation| EntitySlot {
+                generation,
+                alive: false,
+                location: None,
+            })
+            .collect();
+
+        // Indices missing from the free list are handed out last
+        let mut is_listed = vec![false; slot_count];
+        for index in entity_slots.free_indices.iter() {
+            is_listed[*index] = true;
+        }
+        self.free_indices = (0..slot_count).filter(|index| !is_listed[*index]).collect();
+        self.free_indices.extend(entity_slots.free_indices);
+        self.has_restored_slots = true;
+        Ok(())
+    }
+    ///
+    /// Get the bound of the indices entities restored from a save may take, so a corrupted index
+    /// does not allocate slots up to it. Saves with entity slots bound indices by their slot
+    /// count. Saves without, such as bare arrays of entities, only allow indices up to a multiple
+    /// of `loaded_entity_count`.
+    ///
+    pub(crate) fn get_restored_index_bound(&self, loaded_entity_count: usize) -> usize {
+        if self.has_restored_slots {
+            return self.slots.len();
+        }
+        self.slots
+            .len()
+            .max(MIN_RESTORED_INDEX_BOUND)
+            .max(loaded_entity_count.saturating_mul(RESTORED_INDEX_BOUND_FACTOR))
+    }
+    ///
+    /// Raise the generations of this allocator to those of `other` for every index which is not
+    /// alive, adding indices beyond this allocator as free indices, so handles which are stale
+    /// in `other` stay stale when its entities are moved into this allocator.
+    ///
+    pub(crate) fn merge_slots(&mut self, other: &Entities) {
+        for (index, other_slot) in other.slots.iter().enumerate() {
+            match self.slots.get_mut(index) {
+                Some(slot) if !slot.alive => {
+                    slot.generation = slot.generation.max(other_slot.generation)
+                }
+                Some(_) => {}
+                None => {
+                    self.free_indices.push(index);
+                    self.slots.push(EntitySlot {
+                        generation: other_slot.generation,
+                        alive: false,
+                        location: None,
+                    });
+                }
+            }
+        }
+    }
+    ///
+    /// Mark the index of the given entity as alive, used when restoring entities from serialized
+    /// data. Returns the handle of the restored entity, which is the given entity unless handles
+    /// of the index have been handed out up to a later generation. The restored entity then takes
+    /// the generation of the slot, which has not been handed out yet.
+    ///
+    /// Returns an error if the index is not below `index_bound` or an existing index, so a
+    /// corrupted index does not allocate slots up to it.
+    ///
+    pub(crate) fn reserve(
+        &mut self,
+        entity: Entity,
+        index_bound: usize,
+    ) -> Result<Entity, TitanError> {
+        if entity.index >= self.slots.len().max(index_bound) {
+            return Err(TitanError::MalformedSaveData(format!(
+                "Entity index {} is out of bounds",
+                entity.index
+            )));
+        }
+        while self.slots.len() <= entity.index {
+            self.free_indices.push(self.slots.len());
+            self.slots.push(EntitySlot {
+                generation: 0,
+                alive: false,
+                location: None,
+            });
+        }
+        let slot = &mut self.slots[entity.index];
+        if slot.alive {
+            return Err(TitanError::EntityCollision(entity));
+        }
+        slot.generation = slot.generation.max(entity.generation);
+        slot.alive = true;
+        self.alive_count += 1;
+        Ok(Entity {
+            index: entity.index,
+            generation: slot.generation,
+        })
+    }
+    pub(crate) fn free(&mut self, entity: Entity) -> bool {
+        if !self.is_alive(entity) {
+            return false;
+        }
+        let slot = &mut self.slots[entity.index];
+        slot.generation = slot.generation.wrapping_add(1);
+        slot.alive = false;
+        slot.location = None;
+        self.free_indices.push(entity.index);
+        self.alive_count -= 1;
+        true
+    }
+    pub(crate) fn get_alive_count(&self) -> usize {
+        self.alive_count
+    }
+    pub(crate) fn is_alive(&self, entity: Entity) -> bool {
+        self.slots
+            .get(entity.index)
+            .map(|slot| slot.alive && slot.generation == entity.generation)
+            .unwrap_or(false)
+    }
+    ///
+    /// Get the handle of the living entity with the given index, if any.
+    ///
+    pub(crate) fn get_alive_at(&self, index: EntityId) -> Option<Entity> {
+        let slot = self.slots.get(index)?;
+        if !slot.alive {
+            return None;
+        }
+        Some(Entity {
+            index,
+            generation: slot.generation,
+        })
+    }
+    pub(crate) fn get_location(&self, entity: Entity) -> Option<&EntityLocation> {
+        if !self.is_alive(entity) {
+            return None;
+        }
+        self.slots[entity.index].location.as_ref()
+    }
+    pub(crate) fn set_location(&mut self, entity: Entity, location: EntityLocation) {
+        self.slots[entity.index].location = Some(location);
+    }
+    ///
+    /// Update the row of an entity which was moved within its archetype.
+    ///
+    pub(crate) fn set_row(&mut self, entity: Entity, row: usize) {
+        self.slots[entity.index]
+            .location
+            .as_mut()
+            .expect("Could not get location of moved entity")
+            .row = row;
+    }
+}
@@ -0,0 +1,167 @@
+use crate::{bundle::BundleKind, ComponentKind, Entity};
+use std::fmt::{Display, Formatter};
+
+///
+/// Errors returned by the fallible `try_` methods of the `ECS`.
+///
+#[derive(Debug)]
+pub enum TitanError {
+    ///
+    /// No archetype has been registered for the bundle kind.
+    ///
+    UnknownBundleKind(BundleKind),
+    ///
+    /// The component kind has not been registered.
+    ///
+    UnknownComponentKind(ComponentKind),
+    ///
+    /// A bundle contains more than one component of the same kind.
+    ///
+    DuplicateComponentKind(ComponentKind),
+    ///
+    /// The component kind has already been registered or stored by a different component type.
+    ///
+    ComponentKindCollision(ComponentKind),
+    ///
+    /// Serialized data could not be parsed.
+    ///
+    MalformedSaveData(String),
+    ///
+    /// A component could not be serialized.
+    ///
+    SerializationFailed(String),
+    ///
+    /// Serialized data contains an entity whose index is already in use.
+    ///
+    EntityCollision(Entity),
+    ///
+    /// A component vec lock is already held with conflicting access.
+    ///
+    LockConflict(ComponentKind),
+    ///
+    /// A component vec lock was poisoned by a panic while it was held.
+    ///
+    LockPoisoned(ComponentKind),
+    ///
+    /// The resource kind has not been registered.
+    ///
+    UnknownResourceKind(String),
+    ///
+    /// The resource kind has already been registered by a different resource type.
+    ///
+    ResourceKindCollision(String),
+    ///
+    /// A resource lock is already held with conflicting access.
+    ///
+    ResourceLockConflict(String),
+    ///
+    /// A resource lock was poisoned by a panic while it was held.
+    ///
+    ResourceLockPoisoned(String),
+    ///
+    /// A system requires a resource which has not been inserted.
+    ///
+    ResourceNotFound(String),
+    ///
+    /// The stage has not been added to the schedule.
+    ///
+    UnknownStage(String),
+    ///
+    /// The `before` and `after` constraints of the systems of a stage form a cycle.
+    ///
+    SystemOrderCycle(String),
+    ///
+    /// Serialized data was saved at a newer version than the current save version.
+    ///
+    UnsupportedSaveVersion(u32),
+    ///
+    /// Serialized data could not be written or read.
+    ///
+    Io(std::io::Error),
+}
+impl TitanError {
+    pub(crate) fn malformed(message: &str) -> Self {
+        TitanError::MalformedSaveData(message.to_string())
+    }
+}
+impl Display for TitanError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TitanError::UnknownBundleKind(bundle_kind) => write!(
+                f,
+                "Unknown bundle_kind {:?}. Are you sure the archetype has been registered?",
+                bundle_kind.0
+            ),
+            TitanError::UnknownComponentKind(component_kind) => write!(
+                f,
+                "Unknown component_kind {:?}. Are you sure the component has been registered?",
+                component_kind.0
+            ),
+            TitanError::DuplicateComponentKind(component_kind) => write!(
+                f,
+                "Bundle contains component_kind {:?} more than once",
+                component_kind.0
+            ),
+            TitanError::ComponentKindCollision(component_kind) => write!(
+                f,
+                "Component_kind {:?} is already registered by a different type. Consider naming the component with #[component(name = \"...\")]",
+                component_kind.0
+            ),
+            TitanError::MalformedSaveData(message) => write!(f, "Malformed save data: {}", message),
+            TitanError::SerializationFailed(message) => {
+                write!(f, "Could not serialize component: {}", message)
+            }
+            TitanError::EntityCollision(entity) => {
+                write!(f, "Entity index of {:?} is already in use", entity)
+            }
+            TitanError::LockConflict(component_kind) => write!(
+                f,
+                "Component vec of component_kind {:?} is already locked",
+                component_kind.0
+            ),
+            TitanError::LockPoisoned(component_kind) => write!(
+                f,
+                "Component vec lock of component_kind {:?} is poisoned",
+                component_kind.0
+            ),
+            TitanError::UnknownResourceKind(resource_kind) => write!(
+                f,
+                "Unknown resource_kind {:?}. Are you sure the resource has been registered?",
+                resource_kind
+            ),
+            TitanError::ResourceKindCollision(resource_kind) => write!(
+                f,
+                "Resource_kind {:?} is already registered by a different type",
+                resource_kind
+            ),
+            TitanError::ResourceLockConflict(resource) => {
+                write!(f, "Resource {} is already locked", resource)
+            }
+            TitanError::ResourceLockPoisoned(resource) => {
+                write!(f, "Resource lock of {} is poisoned", resource)
+            }
+            TitanError::ResourceNotFound(resource) => write!(
+                f,
+                "Resource {} not found. Are you sure the resource has been inserted?",
+                resource
+            ),
+            TitanError::UnknownStage(stage) => write!(
+                f,
+                "Unknown stage {:?}. Are you sure the stage has been added?",
+                stage
+            ),
+            TitanError::SystemOrderCycle(label) => write!(
+                f,
+                "Systems can not be ordered, {:?} is part of a before / after cycle",
+                label
+            ),
+            TitanError::UnsupportedSaveVersion(save_version) => write!(
+                f,
+                "Save version {} is newer than the current save version",
+                save_version
+            ),
+            TitanError::Io(error) => write!(f, "Could not write or read save data: {}", error),
+        }
+    }
+}
+impl std::error::Error for TitanError {}
@@ -1,23 +1,46 @@
-#![feature(generic_associated_types)]
-#![feature(type_alias_impl_trait)]
+#![feature(impl_trait_in_assoc_type)]
 
-pub use bundle::Bundle;
+pub use bundle::{Bundle, BundleKind};
+pub use entity::{Entity, EntityCollisionPolicy, MapEntities, MapEntitiesFn};
+pub use error::TitanError;
 use query::Query;
+pub use registry::MigrationFn;
 use registry::{RegisterArchetype, RegisterComponent, Registry};
+pub use resource::{ResourceRef, ResourceRefMut};
+pub use schedule::{ExecutionMode, IntoSystemDescriptor, Schedule, SystemDescriptor};
+use serde::{de::DeserializeOwned, Serialize};
 use serialization::Serializable;
+pub use serialization::{
+    Binary, ColumnarJson, Format, Json, SerializeOptions, UnknownComponentPolicy,
+};
+use std::{
+    collections::{HashMap, VecDeque},
+    io::{Read, Write},
+    marker::PhantomData,
+    mem,
+};
 use storage::Storage;
+pub use storage::{ComponentRef, ComponentRefMut, RemovalClearPolicy, RemovedComponents};
+pub use system::{Access, FunctionSystem, IntoSystem, IsSystem, System, SystemParam, SystemQuery};
 pub use titan_macros::component;
 
 mod bundle;
+mod entity;
+mod error;
 mod query;
 mod registry;
+mod resource;
+mod schedule;
 mod serialization;
 mod storage;
+mod system;
 
+use query::Filter;
 pub use query::ResultIter as ResultIteration;
+pub use query::{Added, Changed, Mut, With, Without};
 
 ///
-/// Type for all enitity identifiers.
+/// Type for all enitity indices. See `Entity` for the generational handle.
 ///
 type EntityId = usize;
 
@@ -29,22 +52,116 @@ pub struct ComponentKind(pub String);
 
 ///
 /// MetaData methods for components. This trait is implemented by the `component` attribute macro.
-/// These methods are used by titan internals.
+/// These methods are used by titan internals. Components are `Send` and `Sync`, so systems may
+/// run on several threads.
 ///
-pub trait ComponentMeta {
+pub trait ComponentMeta: Send + Sync {
     fn get_component_kind() -> ComponentKind;
+    ///
+    /// Function remapping the entity references held by the component, if it has any.
+    ///
+    fn get_map_entities_fn() -> Option<MapEntitiesFn<Self>> {
+        None
+    }
+    ///
+    /// Whether the component is never saved. Set with `#[component(transient)]`.
+    ///
+    fn is_transient() -> bool {
+        false
+    }
 }
 
 pub struct ECS {
     pub registry: Registry,
     storage: Storage,
+    ///
+    /// Tick at which components are currently added and changed. Ticks are 64 bit, as a
+    /// `Schedule` advances them for every batch of systems and they are never wrapped around.
+    ///
+    change_tick: u64,
+    ///
+    /// Tick which ended with the last call to `increment_change_tick`.
+    ///
+    last_change_tick: u64,
+    ///
+    /// Change ticks at which the current and the recently ended ticks started, oldest first. A
+    /// `Schedule` advances the change tick once per system within a single tick.
+    ///
+    tick_starts: VecDeque<u64>,
+    removal_clear_policy: RemovalClearPolicy,
 }
 impl ECS {
     ///
     /// Register new component.
     ///
+    /// The `ComponentKind` of a component is the name of its struct, unless given with
+    /// `#[component(name = "game::Position")]`.
+    ///
     pub fn register_component<T: RegisterComponent>(&mut self) {
-        self.registry.register_component::<T>();
+        self.try_register_component::<T>()
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+    ///
+    /// Register new component.
+    /// Returns an error if a different component type with the same `ComponentKind` has already
+    /// been registered.
+    ///
+    pub fn try_register_component<T: RegisterComponent>(&mut self) -> Result<(), TitanError> {
+        self.registry.register_component::<T>()
+    }
+    ///
+    /// Set the version written into saves. Saves written at an older version are upgraded by the
+    /// registered migrations when loaded, saves written at a newer version can not be loaded.
+    ///
+    pub fn set_save_version(&mut self, save_version: u32) {
+        self.registry.set_save_version(save_version);
+    }
+    ///
+    /// Register a migration upgrading the serialized value of component `T` from `from_version`
+    /// to the next version. Migrations are applied in order of version before deserialization.
+    ///
+    /// Migrations can not be applied to `Binary` saves, loading a `Binary` save which requires a
+    /// migration returns an error.
+    ///
+    pub fn register_migration<T: ComponentMeta>(
+        &mut self,
+        from_version: u32,
+        migration: MigrationFn,
+    ) {
+        self.registry
+            .register_migration::<T>(from_version, migration);
+    }
+    ///
+    /// Set how loading handles components of kinds which have not been registered. Defaults to
+    /// `UnknownComponentPolicy::Error`.
+    ///
+    pub fn set_unknown_component_policy(
+        &mut self,
+        unknown_component_policy: UnknownComponentPolicy,
+    ) {
+        self.registry
+            .set_unknown_component_policy(unknown_component_policy);
+    }
+    ///
+    /// Register the resource `R` to be saved under `resource_kind`, alongside entities.
+    /// Resources which have not been registered are never saved.
+    ///
+    pub fn register_resource<R>(&mut self, resource_kind: &str)
+    where
+        R: Serialize + DeserializeOwned + Send + Sync + 'static,
+    {
+        self.try_register_resource::<R>(resource_kind)
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+    ///
+    /// Register the resource `R` to be saved under `resource_kind`, alongside entities.
+    /// Returns an error if the resource kind is already registered by a different type.
+    ///
+    pub fn try_register_resource<R>(&mut self, resource_kind: &str) -> Result<(), TitanError>
+    where
+        R: Serialize + DeserializeOwned + Send + Sync + 'static,
+    {
+        self.registry.register_resource::<R>(resource_kind)
     }
     ///
     /// Register new archetype.
@@ -52,33 +169,598 @@ impl ECS {
     ///
     /// Ensure all components types in bundle have been registered before calling this method.
     ///
+    /// Archetypes are also registered automatically when spawned or when an entity migrates into
+    /// them, provided all of their components have been registered.
+    ///
     pub fn register_archetype<T: RegisterArchetype>(&mut self) {
-        self.registry.register_archetype::<T>();
+        self.try_register_archetype::<T>()
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+    ///
+    /// Register new archetype.
+    /// Returns an error if any component type in the bundle has not been registered.
+    ///
+    pub fn try_register_archetype<T: RegisterArchetype>(&mut self) -> Result<(), TitanError> {
+        self.registry.register_archetype::<T>()
     }
     ///
     /// Spawn bundle of components into new entity.
+    /// Returns the handle of the new entity.
+    ///
+    pub fn spawn_bundle<T: Bundle + 'static>(&mut self, bundle: T) -> Entity {
+        self.try_spawn_bundle(bundle)
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+    ///
+    /// Spawn bundle of components into new entity.
+    /// Returns an error if the bundle contains more than one component of the same kind, or if a
+    /// component kind is used by a different type in the archetype of the bundle.
+    ///
+    pub fn try_spawn_bundle<T: Bundle + 'static>(
+        &mut self,
+        bundle: T,
+    ) -> Result<Entity, TitanError> {
+        let entity = self.storage.spawn(bundle, self.change_tick)?;
+        self.registry.auto_register_bundle::<T>();
+        Ok(entity)
+    }
+    ///
+    /// Despawn the given entity, removing all of its components.
+    /// Returns `false` if the entity is not alive, including when the handle is stale.
+    ///
+    pub fn despawn(&mut self, entity: Entity) -> bool {
+        self.storage.despawn(entity, self.change_tick)
+    }
+    ///
+    /// Check if the given entity handle refers to a living entity.
+    ///
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        self.storage.is_alive(entity)
+    }
+    ///
+    /// Get read access to the component `T` of the given entity.
+    /// Returns `None` if the entity is not alive or does not have the component.
+    ///
+    pub fn get<T: 'static + ComponentMeta>(&self, entity: Entity) -> Option<ComponentRef<'_, T>> {
+        self.try_get::<T>(entity)
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+    ///
+    /// Get read access to the component `T` of the given entity.
+    /// Returns an error if the component vec is already locked for writing, or if it holds a
+    /// different type of the same component kind.
     ///
-    pub fn spawn_bundle<T: Bundle + 'static>(&mut self, bundle: T) {
-        self.storage.spawn(bundle)
+    pub fn try_get<T: 'static + ComponentMeta>(
+        &self,
+        entity: Entity,
+    ) -> Result<Option<ComponentRef<'_, T>>, TitanError> {
+        self.storage.get::<T>(entity)
+    }
+    ///
+    /// Get write access to the component `T` of the given entity, which is recorded as changed
+    /// when it is written to.
+    /// Returns `None` if the entity is not alive or does not have the component.
+    ///
+    pub fn get_mut<T: 'static + ComponentMeta>(
+        &self,
+        entity: Entity,
+    ) -> Option<ComponentRefMut<'_, T>> {
+        self.try_get_mut::<T>(entity)
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+    ///
+    /// Get write access to the component `T` of the given entity.
+    /// Returns an error if the component vec is already locked.
+    ///
+    pub fn try_get_mut<T: 'static + ComponentMeta>(
+        &self,
+        entity: Entity,
+    ) -> Result<Option<ComponentRefMut<'_, T>>, TitanError> {
+        self.storage.get_mut::<T>(entity, self.change_tick)
+    }
+    ///
+    /// Insert a component into the given entity, moving the entity into the archetype of its new
+    /// component combination. An existing component of the same kind is replaced in place.
+    /// Returns `false` if the entity is not alive.
+    ///
+    pub fn insert_component<T: 'static + ComponentMeta>(
+        &mut self,
+        entity: Entity,
+        component: T,
+    ) -> bool {
+        self.try_insert_component(entity, component)
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+    ///
+    /// Insert a component into the given entity.
+    /// Returns an error if the component kind is used by a different type in the entity's new
+    /// archetype.
+    ///
+    pub fn try_insert_component<T: 'static + ComponentMeta>(
+        &mut self,
+        entity: Entity,
+        component: T,
+    ) -> Result<bool, TitanError> {
+        let inserted = self
+            .storage
+            .insert_component(entity, component, self.change_tick)?;
+        if T::is_transient() {
+            self.registry
+                .register_transient_component_kind(T::get_component_kind());
+        }
+        self.auto_register_entity_archetype(entity);
+        Ok(inserted)
+    }
+    ///
+    /// Remove the component `T` from the given entity, moving the entity into the archetype of its
+    /// remaining component combination.
+    /// Returns `None` if the entity is not alive or does not have the component.
+    ///
+    pub fn remove_component<T: 'static + ComponentMeta>(&mut self, entity: Entity) -> Option<T> {
+        self.try_remove_component::<T>(entity)
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+    ///
+    /// Remove the component `T` from the given entity.
+    /// Returns an error if the component kind is used by a different type in the entity's
+    /// archetype.
+    ///
+    pub fn try_remove_component<T: 'static + ComponentMeta>(
+        &mut self,
+        entity: Entity,
+    ) -> Result<Option<T>, TitanError> {
+        let component = self
+            .storage
+            .remove_component::<T>(entity, self.change_tick)?;
+        self.auto_register_entity_archetype(entity);
+        Ok(component)
+    }
+    fn auto_register_entity_archetype(&mut self, entity: Entity) {
+        if let Some(bundle_kind) = self.storage.get_bundle_kind(entity) {
+            let storage = &self.storage;
+            self.registry.auto_register_bundle_kind(bundle_kind, || {
+                storage
+                    .get_component_kinds(entity)
+                    .expect("Could not get component kinds of living entity")
+            });
+        }
+    }
+    ///
+    /// Insert a world wide resource, such as a game clock or configuration, stored by type.
+    /// Returns the replaced resource of the same type, if any.
+    ///
+    pub fn insert_resource<R: 'static + Send + Sync>(&mut self, resource: R) -> Option<R> {
+        self.storage.resources.insert(resource)
+    }
+    ///
+    /// Remove the resource of type `R`.
+    /// Returns `None` if there is no resource of the type.
+    ///
+    pub fn remove_resource<R: 'static + Send + Sync>(&mut self) -> Option<R> {
+        self.storage.resources.remove::<R>()
+    }
+    pub fn has_resource<R: 'static + Send + Sync>(&self) -> bool {
+        self.storage.resources.contains::<R>()
+    }
+    ///
+    /// Get read access to the resource of type `R`.
+    /// Returns `None` if there is no resource of the type.
+    ///
+    pub fn resource<R: 'static + Send + Sync>(&self) -> Option<ResourceRef<'_, R>> {
+        self.try_resource::<R>()
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+    ///
+    /// Get read access to the resource of type `R`.
+    /// Returns an error if the resource is already locked for writing.
+    ///
+    pub fn try_resource<R: 'static + Send + Sync>(
+        &self,
+    ) -> Result<Option<ResourceRef<'_, R>>, TitanError> {
+        self.storage.resources.get::<R>()
+    }
+    ///
+    /// Get write access to the resource of type `R`.
+    /// Returns `None` if there is no resource of the type.
+    ///
+    pub fn resource_mut<R: 'static + Send + Sync>(&self) -> Option<ResourceRefMut<'_, R>> {
+        self.try_resource_mut::<R>()
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+    ///
+    /// Get write access to the resource of type `R`.
+    /// Returns an error if the resource is already locked.
+    ///
+    pub fn try_resource_mut<R: 'static + Send + Sync>(
+        &self,
+    ) -> Result<Option<ResourceRefMut<'_, R>>, TitanError> {
+        self.storage.resources.get_mut::<R>()
+    }
+    ///
+    /// Get the current world tick, at which components are added and changed.
+    ///
+    pub fn get_change_tick(&self) -> u64 {
+        self.change_tick
+    }
+    ///
+    /// Advance the world tick.
+    /// Returns the tick which ended, to be given as the last run tick of a later `query_since`.
+    ///
+    pub fn increment_change_tick(&mut self) -> u64 {
+        self.last_change_tick = self.change_tick;
+        self.change_tick += 1;
+
+        // Forget removals which happened before the oldest kept tick
+        self.tick_starts.push_back(self.change_tick);
+        let kept_tick_count = match self.removal_clear_policy {
+            RemovalClearPolicy::KeepTicks(ticks) => ticks as usize + 1,
+            RemovalClearPolicy::Manual => 1,
+        };
+        while self.tick_starts.len() > kept_tick_count {
+            self.tick_starts.pop_front();
+        }
+        if let RemovalClearPolicy::KeepTicks(_) = self.removal_clear_policy {
+            self.storage.clear_removals_before(self.tick_starts[0]);
+        }
+        self.last_change_tick
+    }
+    ///
+    /// Advance the change tick within the current tick, so systems run later in the tick can tell
+    /// apart changes made before and after they ran.
+    ///
+    pub(crate) fn advance_change_tick(&mut self) {
+        self.change_tick += 1;
+    }
+    ///
+    /// Set when removals of components are forgotten. Defaults to keeping the removals of the
+    /// current and the last ended tick.
+    ///
+    pub fn set_removal_clear_policy(&mut self, policy: RemovalClearPolicy) {
+        self.removal_clear_policy = policy;
+    }
+    ///
+    /// Get the entities which had the component `T` removed, including by despawning, since the
+    /// last call to `increment_change_tick`. Deserializing removes the components of every entity
+    /// of the replaced storage.
+    ///
+    pub fn removed_components<T: 'static + ComponentMeta>(&self) -> RemovedComponents<'_, T> {
+        self.removed_components_since::<T>(self.last_change_tick)
+    }
+    ///
+    /// Get the entities which had the component `T` removed, including by despawning, after
+    /// `last_run_tick`. Removals which have already been cleared are not included.
+    ///
+    pub fn removed_components_since<T: 'static + ComponentMeta>(
+        &self,
+        last_run_tick: u64,
+    ) -> RemovedComponents<'_, T> {
+        RemovedComponents {
+            removals: self.storage.get_removals(&T::get_component_kind()),
+            last_run_tick,
+            phantom: PhantomData,
+        }
+    }
+    ///
+    /// Forget all removals of components.
+    ///
+    pub fn clear_removed_components(&mut self) {
+        self.storage.clear_removals();
     }
     ///
     /// Query the storage for all components in archetypes which AT LEAST match the given query
     /// type.
     ///
+    /// `Added` and `Changed` match components added or changed since the last call to
+    /// `increment_change_tick`. Components requested with `&mut` are yielded as `Mut`, which
+    /// records the component as changed when it is written to.
+    ///
     pub fn query<'fetch, T: Query<'fetch>>(&'fetch self) -> T::ResultType {
-        self.storage.query::<T>()
+        self.try_query::<T>()
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+    ///
+    /// Query the storage for all components in archetypes which AT LEAST match the given query
+    /// type.
+    /// Returns an error if a requested component vec is already locked with conflicting access.
+    ///
+    pub fn try_query<'fetch, T: Query<'fetch>>(&'fetch self) -> Result<T::ResultType, TitanError> {
+        self.try_query_since::<T>(self.last_change_tick)
+    }
+    ///
+    /// Query the storage for all components in archetypes which AT LEAST match the given query
+    /// type.
+    ///
+    /// `Added` and `Changed` match components added or changed after `last_run_tick`, which is
+    /// usually the tick returned by `increment_change_tick` when the querying code last ran.
+    ///
+    pub fn query_since<'fetch, T: Query<'fetch>>(
+        &'fetch self,
+        last_run_tick: u64,
+    ) -> T::ResultType {
+        self.try_query_since::<T>(last_run_tick)
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+    ///
+    /// Query the storage for all components in archetypes which AT LEAST match the given query
+    /// type, matching `Added` and `Changed` against `last_run_tick`.
+    /// Returns an error if a requested component vec is already locked with conflicting access.
+    ///
+    pub fn try_query_since<'fetch, T: Query<'fetch>>(
+        &'fetch self,
+        last_run_tick: u64,
+    ) -> Result<T::ResultType, TitanError> {
+        self.try_query_filtered_since::<T, ()>(last_run_tick)
+    }
+    ///
+    /// Query the storage for all components of entities which match both the query type and the
+    /// filter `F`, a tuple of `With`, `Without`, `Added` and `Changed`. Nothing is yielded for the
+    /// filter, e.g. `query_filtered::<(&Position,), (With<Player>,)>()` yields `&Position`.
+    ///
+    pub fn query_filtered<'fetch, T: Query<'fetch>, F: Filter>(&'fetch self) -> T::ResultType {
+        self.try_query_filtered::<T, F>()
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+    ///
+    /// Query the storage for all components of entities which match both the query type and the
+    /// filter `F`.
+    /// Returns an error if a requested component vec is already locked with conflicting access.
+    ///
+    pub fn try_query_filtered<'fetch, T: Query<'fetch>, F: Filter>(
+        &'fetch self,
+    ) -> Result<T::ResultType, TitanError> {
+        self.try_query_filtered_since::<T, F>(self.last_change_tick)
+    }
+    ///
+    /// Query the storage for all components of entities which match both the query type and the
+    /// filter `F`, matching `Added` and `Changed` against `last_run_tick`.
+    ///
+    pub fn query_filtered_since<'fetch, T: Query<'fetch>, F: Filter>(
+        &'fetch self,
+        last_run_tick: u64,
+    ) -> T::ResultType {
+        self.try_query_filtered_since::<T, F>(last_run_tick)
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+    ///
+    /// Query the storage for all components of entities which match both the query type and the
+    /// filter `F`, matching `Added` and `Changed` against `last_run_tick`.
+    /// Returns an error if a requested component vec is already locked with conflicting access.
+    ///
+    pub fn try_query_filtered_since<'fetch, T: Query<'fetch>, F: Filter>(
+        &'fetch self,
+        last_run_tick: u64,
+    ) -> Result<T::ResultType, TitanError> {
+        self.storage.query::<T, F>(last_run_tick, self.change_tick)
     }
     ///
     /// Serialize entities to JSON.
     ///
     pub fn serialize(&self) -> String {
-        self.storage.serialize(&self.registry)
+        self.try_serialize()
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+    ///
+    /// Serialize entities to JSON.
+    /// Returns an error if an archetype has not been registered or a component vec is locked.
+    ///
+    pub fn try_serialize(&self) -> Result<String, TitanError> {
+        self.try_serialize_with::<Json>()
+    }
+    ///
+    /// Serialize the entities and components selected by `options` to JSON.
+    ///
+    pub fn serialize_filtered(&self, options: &SerializeOptions) -> String {
+        self.try_serialize_filtered(options)
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+    ///
+    /// Serialize the entities and components selected by `options` to JSON.
+    /// Returns an error if a selected component has not been registered or a component vec is
+    /// locked.
+    ///
+    pub fn try_serialize_filtered(&self, options: &SerializeOptions) -> Result<String, TitanError> {
+        self.try_serialize_filtered_with::<Json>(options)
+    }
+    ///
+    /// Serialize entities using the given format, such as `Json` or `Binary`.
+    ///
+    pub fn serialize_with<F: Format>(&self) -> <F::Serial as ToOwned>::Owned {
+        self.try_serialize_with::<F>()
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+    ///
+    /// Serialize entities using the given format, such as `Json` or `Binary`.
+    /// Returns an error if a component has not been registered or a component vec is locked.
+    ///
+    pub fn try_serialize_with<F: Format>(
+        &self,
+    ) -> Result<<F::Serial as ToOwned>::Owned, TitanError> {
+        self.try_serialize_filtered_with::<F>(&SerializeOptions::default())
+    }
+    ///
+    /// Serialize the entities and components selected by `options` using the given format.
+    ///
+    pub fn serialize_filtered_with<F: Format>(
+        &self,
+        options: &SerializeOptions,
+    ) -> <F::Serial as ToOwned>::Owned {
+        self.try_serialize_filtered_with::<F>(options)
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+    ///
+    /// Serialize the entities and components selected by `options` using the given format.
+    /// Returns an error if a selected component has not been registered or a component vec is
+    /// locked.
+    ///
+    pub fn try_serialize_filtered_with<F: Format>(
+        &self,
+        options: &SerializeOptions,
+    ) -> Result<<F::Serial as ToOwned>::Owned, TitanError> {
+        F::serialize(&self.storage, &self.registry, options)
+    }
+    ///
+    /// Serialize entities to JSON, writing each entity to `writer` as soon as it is serialized.
+    ///
+    /// Writes are not buffered, wrap unbuffered writers such as files in a `BufWriter`.
+    ///
+    pub fn serialize_to<W: Write>(&self, writer: W) {
+        self.try_serialize_to(writer)
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+    ///
+    /// Serialize entities to JSON, writing each entity to `writer` as soon as it is serialized.
+    /// Returns an error if an archetype has not been registered, a component vec is locked or
+    /// writing fails. Entities written before the error are not rolled back.
+    ///
+    pub fn try_serialize_to<W: Write>(&self, writer: W) -> Result<(), TitanError> {
+        self.storage
+            .serialize_to(&self.registry, &SerializeOptions::default(), writer)
     }
     ///
     /// Replaces storage with entities from JSON.
     ///
     pub fn deserialize(&mut self, serial: &str) {
-        self.storage = Storage::deserialize(serial, &self.registry);
+        self.try_deserialize(serial)
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+    ///
+    /// Replaces storage with entities from JSON.
+    /// Returns an error, leaving the storage untouched, if the JSON is malformed or contains an
+    /// archetype which has not been registered.
+    ///
+    pub fn try_deserialize(&mut self, serial: &str) -> Result<(), TitanError> {
+        self.try_deserialize_with::<Json>(serial)
+    }
+    ///
+    /// Replaces storage with entities from JSON, restoring each entity as soon as it is read
+    /// from `reader`.
+    ///
+    /// Reads are not buffered, wrap unbuffered readers such as files in a `BufReader`.
+    ///
+    pub fn deserialize_from<R: Read>(&mut self, reader: R) {
+        self.try_deserialize_from(reader)
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+    ///
+    /// Replaces storage with entities from JSON, restoring each entity as soon as it is read
+    /// from `reader`.
+    /// Returns an error, leaving the storage untouched, if reading fails, the JSON is malformed or
+    /// contains an archetype which has not been registered.
+    ///
+    pub fn try_deserialize_from<R: Read>(&mut self, reader: R) -> Result<(), TitanError> {
+        let storage = Storage::deserialize_from(reader, &self.registry)?;
+        self.replace_storage(storage);
+        self.auto_register_storage_archetypes();
+        Ok(())
+    }
+    ///
+    /// Replaces storage with entities serialized using the given format.
+    ///
+    pub fn deserialize_with<F: Format>(&mut self, serial: &F::Serial) {
+        self.try_deserialize_with::<F>(serial)
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+    ///
+    /// Replaces storage with entities serialized using the given format.
+    /// Returns an error, leaving the storage untouched, if the data is malformed or contains a
+    /// component which has not been registered.
+    ///
+    pub fn try_deserialize_with<F: Format>(
+        &mut self,
+        serial: &F::Serial,
+    ) -> Result<(), TitanError> {
+        let storage = F::deserialize(serial, &self.registry)?;
+        self.replace_storage(storage);
+        self.auto_register_storage_archetypes();
+        Ok(())
+    }
+    ///
+    /// Spawn entities from JSON into the existing storage, resolving entities whose index is
+    /// already in use according to `policy`.
+    /// Returns the new handle of each loaded entity by its handle in the JSON.
+    ///
+    /// Entity references held by loaded components implementing `MapEntities` are remapped to
+    /// the new handles. Loaded resources are only inserted if there is no resource of the same
+    /// type yet.
+    ///
+    pub fn load_additive(
+        &mut self,
+        serial: &str,
+        policy: EntityCollisionPolicy,
+    ) -> HashMap<Entity, Entity> {
+        self.try_load_additive(serial, policy)
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+    ///
+    /// Spawn entities from JSON into the existing storage, resolving entities whose index is
+    /// already in use according to `policy`.
+    /// Returns an error, leaving the storage untouched, if the JSON is malformed, contains an
+    /// archetype which has not been registered or, with `EntityCollisionPolicy::Error`, contains
+    /// an entity whose index is already in use.
+    ///
+    pub fn try_load_additive(
+        &mut self,
+        serial: &str,
+        policy: EntityCollisionPolicy,
+    ) -> Result<HashMap<Entity, Entity>, TitanError> {
+        self.try_load_additive_with::<Json>(serial, policy)
+    }
+    ///
+    /// Spawn entities serialized using the given format into the existing storage, resolving
+    /// entities whose index is already in use according to `policy`.
+    /// Returns the new handle of each loaded entity by its handle in the serialized data.
+    ///
+    /// Entity references held by loaded components implementing `MapEntities` are remapped to
+    /// the new handles. Loaded resources are only inserted if there is no resource of the same
+    /// type yet.
+    ///
+    pub fn load_additive_with<F: Format>(
+        &mut self,
+        serial: &F::Serial,
+        policy: EntityCollisionPolicy,
+    ) -> HashMap<Entity, Entity> {
+        self.try_load_additive_with::<F>(serial, policy)
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+    ///
+    /// Spawn entities serialized using the given format into the existing storage, resolving
+    /// entities whose index is already in use according to `policy`.
+    /// Returns an error, leaving the storage untouched, if the data is malformed, contains a
+    /// component which has not been registered or, with `EntityCollisionPolicy::Error`, contains
+    /// an entity whose index is already in use.
+    ///
+    pub fn try_load_additive_with<F: Format>(
+        &mut self,
+        serial: &F::Serial,
+        policy: EntityCollisionPolicy,
+    ) -> Result<HashMap<Entity, Entity>, TitanError> {
+        let storage = F::deserialize(serial, &self.registry)?;
+        let entity_map = self
+            .storage
+            .merge(storage, policy, &self.registry, self.change_tick)?;
+        self.auto_register_storage_archetypes();
+        Ok(entity_map)
+    }
+    ///
+    /// Replace the storage with loaded storage. Resources which were not loaded are kept.
+    ///
+    /// Every component of the replaced entities is recorded as removed, including for entities
+    /// which are loaded again, and earlier removals are kept.
+    ///
+    fn replace_storage(&mut self, mut storage: Storage) {
+        let mut resources = mem::take(&mut self.storage.resources);
+        resources.append(mem::take(&mut storage.resources));
+        storage.resources = resources;
+        storage.fill_ticks(self.change_tick);
+        let replaced_storage = mem::replace(&mut self.storage, storage);
+        self.storage
+            .record_replaced_removals(replaced_storage, self.change_tick);
+    }
+    fn auto_register_storage_archetypes(&mut self) {
+        for (bundle_kind, component_kinds) in self.storage.get_archetype_component_kinds() {
+            self.registry
+                .auto_register_bundle_kind(&bundle_kind, || component_kinds);
+        }
     }
 }
 impl Default for ECS {
@@ -86,6 +768,10 @@ impl Default for ECS {
         Self {
             registry: Registry::new(),
             storage: Storage::new(),
+            change_tick: 1,
+            last_change_tick: 0,
+            tick_starts: VecDeque::from([1]),
+            removal_clear_policy: RemovalClearPolicy::default(),
         }
     }
 }
@@ -1,10 +1,12 @@
-use crate::storage::{Archetype, Storage};
-use crate::ComponentMeta;
-use itertools::izip;
+use crate::storage::{Archetype, ComponentTicks, Storage};
+use crate::{Access, ComponentMeta, Entity, TitanError};
+use itertools::{izip, Either};
 use paste::paste;
 use std::{
     fmt::Debug,
+    iter,
     marker::PhantomData,
+    ops::{Deref, DerefMut},
     sync::{RwLockReadGuard, RwLockWriteGuard},
 };
 
@@ -19,17 +21,42 @@ use std::{
 /// The `Result{#}` struct also implements `ResultIter`, exposing the `iter` method to the caller
 /// of the `query` method.
 ///
+/// Rows whose components were added or changed after `last_run_tick` are matched by `Added` and
+/// `Changed`, and rows written to are recorded as changed at `change_tick`. Only entities which
+/// also pass the filter `F` are yielded.
+///
 pub trait Query<'fetch> {
     type ResultType;
-    fn query(storage: &'fetch Storage) -> Self::ResultType;
+    fn query<F: Filter>(
+        storage: &'fetch Storage,
+        last_run_tick: u64,
+        change_tick: u64,
+    ) -> Result<Self::ResultType, TitanError>;
+    ///
+    /// Add the component kinds read and written by the query to `access`.
+    ///
+    fn add_access(access: &mut Access);
 }
 
 ///
-/// Main `Parameter` trait, defining only the associated type `ParameterFetch` which contains some
-/// struct implementing `ParameterFetch`.
+/// Main `Parameter` trait, defining the associated type `ParameterFetch` which contains some
+/// struct implementing `ParameterFetch`, and whether an archetype can be queried by the
+/// parameter.
+///
+/// Parameters which only match some rows of a matching archetype, such as `Added`, also clear
+/// the rows they do not match in `filter_rows`.
+///
+/// Parameters which read or write components declare so in `add_access`, which lets a parallel
+/// `Schedule` tell which systems may run at the same time.
 ///
 pub trait Parameter {
     type ParameterFetch: for<'borrow> ParameterFetch<'borrow>;
+    fn matches_archetype(archetype: &Archetype) -> bool;
+    fn filters_rows() -> bool {
+        false
+    }
+    fn filter_rows(_archetype: &Archetype, _last_run_tick: u64, _rows: &mut [bool]) {}
+    fn add_access(_access: &mut Access) {}
 }
 
 ///
@@ -40,6 +67,12 @@ where
     T: 'static + ComponentMeta,
 {
     type ParameterFetch = ParameterFetchRead<T>;
+    fn matches_archetype(archetype: &Archetype) -> bool {
+        archetype.has_component::<T>()
+    }
+    fn add_access(access: &mut Access) {
+        access.add_read(T::get_component_kind());
+    }
 }
 ///
 /// Implementations for `Parameter` for Write.
@@ -49,6 +82,200 @@ where
     T: 'static + ComponentMeta,
 {
     type ParameterFetch = ParameterFetchWrite<T>;
+    fn matches_archetype(archetype: &Archetype) -> bool {
+        archetype.has_component::<T>()
+    }
+    fn add_access(access: &mut Access) {
+        access.add_write(T::get_component_kind());
+    }
+}
+///
+/// Implementations for `Parameter` for optional Read.
+///
+impl<T> Parameter for Option<&T>
+where
+    T: 'static + ComponentMeta,
+{
+    type ParameterFetch = ParameterFetchOptionRead<T>;
+    fn matches_archetype(_archetype: &Archetype) -> bool {
+        true
+    }
+    fn add_access(access: &mut Access) {
+        access.add_read(T::get_component_kind());
+    }
+}
+///
+/// Implementations for `Parameter` for optional Write.
+///
+impl<T> Parameter for Option<&mut T>
+where
+    T: 'static + ComponentMeta,
+{
+    type ParameterFetch = ParameterFetchOptionWrite<T>;
+    fn matches_archetype(_archetype: &Archetype) -> bool {
+        true
+    }
+    fn add_access(access: &mut Access) {
+        access.add_write(T::get_component_kind());
+    }
+}
+///
+/// Filter parameter matching archetypes which have the component `T`. Takes no lock and yields
+/// `()` for each entity, or nothing when given in the filter of `ECS::query_filtered`.
+///
+#[derive(Debug)]
+pub struct With<T> {
+    phantom: PhantomData<T>,
+}
+///
+/// Filter parameter matching archetypes which do not have the component `T`. Takes no lock and
+/// yields `()` for each entity, or nothing when given in the filter of `ECS::query_filtered`.
+///
+#[derive(Debug)]
+pub struct Without<T> {
+    phantom: PhantomData<T>,
+}
+///
+/// Implementations for `Parameter` for With.
+///
+impl<T> Parameter for With<T>
+where
+    T: 'static + ComponentMeta,
+{
+    type ParameterFetch = ParameterFetchFilter;
+    fn matches_archetype(archetype: &Archetype) -> bool {
+        archetype.has_component::<T>()
+    }
+}
+///
+/// Implementations for `Parameter` for Without.
+///
+impl<T> Parameter for Without<T>
+where
+    T: 'static + ComponentMeta,
+{
+    type ParameterFetch = ParameterFetchFilter;
+    fn matches_archetype(archetype: &Archetype) -> bool {
+        !archetype.has_component::<T>()
+    }
+}
+///
+/// Filter parameter matching entities whose component `T` was added since the query last ran.
+/// Takes no lock and yields `()` for each entity, or nothing when given in the filter of
+/// `ECS::query_filtered`.
+///
+#[derive(Debug)]
+pub struct Added<T> {
+    phantom: PhantomData<T>,
+}
+///
+/// Filter parameter matching entities whose component `T` was added or written to since the
+/// query last ran. Takes no lock and yields `()` for each entity, or nothing when given in the
+/// filter of `ECS::query_filtered`.
+///
+#[derive(Debug)]
+pub struct Changed<T> {
+    phantom: PhantomData<T>,
+}
+///
+/// Implementations for `Parameter` for Added.
+///
+impl<T> Parameter for Added<T>
+where
+    T: 'static + ComponentMeta,
+{
+    type ParameterFetch = ParameterFetchFilter;
+    fn matches_archetype(archetype: &Archetype) -> bool {
+        archetype.has_component::<T>()
+    }
+    fn filters_rows() -> bool {
+        true
+    }
+    fn filter_rows(archetype: &Archetype, last_run_tick: u64, rows: &mut [bool]) {
+        let ticks = archetype.get_component_ticks::<T>();
+        for (row, matches) in rows.iter_mut().enumerate() {
+            *matches &= ticks.is_some_and(|ticks| ticks.is_added_since(row, last_run_tick));
+        }
+    }
+    fn add_access(access: &mut Access) {
+        access.add_read(T::get_component_kind());
+    }
+}
+///
+/// Implementations for `Parameter` for Changed.
+///
+impl<T> Parameter for Changed<T>
+where
+    T: 'static + ComponentMeta,
+{
+    type ParameterFetch = ParameterFetchFilter;
+    fn matches_archetype(archetype: &Archetype) -> bool {
+        archetype.has_component::<T>()
+    }
+    fn filters_rows() -> bool {
+        true
+    }
+    fn filter_rows(archetype: &Archetype, last_run_tick: u64, rows: &mut [bool]) {
+        let ticks = archetype.get_component_ticks::<T>();
+        for (row, matches) in rows.iter_mut().enumerate() {
+            *matches &= ticks.is_some_and(|ticks| ticks.is_changed_since(row, last_run_tick));
+        }
+    }
+    fn add_access(access: &mut Access) {
+        access.add_read(T::get_component_kind());
+    }
+}
+///
+/// Marker for parameters which only filter entities, so they may be given in the filter of a
+/// query instead of the query itself.
+///
+pub trait FilterParameter: Parameter {}
+impl<T: 'static + ComponentMeta> FilterParameter for With<T> {}
+impl<T: 'static + ComponentMeta> FilterParameter for Without<T> {}
+impl<T: 'static + ComponentMeta> FilterParameter for Added<T> {}
+impl<T: 'static + ComponentMeta> FilterParameter for Changed<T> {}
+///
+/// Marker for filter parameters which match whole archetypes and never filter rows.
+///
+pub trait ArchetypeFilterParameter: FilterParameter {}
+impl<T: 'static + ComponentMeta> ArchetypeFilterParameter for With<T> {}
+impl<T: 'static + ComponentMeta> ArchetypeFilterParameter for Without<T> {}
+
+///
+/// Filter of a query, implemented for `()` and tuples of `FilterParameter`s such as
+/// `(With<Player>, Changed<Position>)`. Unlike filter parameters in the query itself, filters
+/// yield nothing.
+///
+pub trait Filter {
+    fn matches_archetype(archetype: &Archetype) -> bool;
+    fn filters_rows() -> bool;
+    fn filter_rows(archetype: &Archetype, last_run_tick: u64, rows: &mut [bool]);
+    fn add_access(access: &mut Access);
+}
+impl Filter for () {
+    fn matches_archetype(_archetype: &Archetype) -> bool {
+        true
+    }
+    fn filters_rows() -> bool {
+        false
+    }
+    fn filter_rows(_archetype: &Archetype, _last_run_tick: u64, _rows: &mut [bool]) {}
+    fn add_access(_access: &mut Access) {}
+}
+///
+/// Filter which only matches archetypes, implemented for tuples of `With` and `Without` such as
+/// `(With<Persistent>, Without<Player>)`.
+///
+pub trait ArchetypeFilter: Filter {}
+
+///
+/// Implementations for `Parameter` for Entity.
+///
+impl Parameter for Entity {
+    type ParameterFetch = ParameterFetchEntity;
+    fn matches_archetype(_archetype: &Archetype) -> bool {
+        true
+    }
 }
 
 ///
@@ -56,7 +283,10 @@ where
 ///
 pub trait ParameterFetch<'fetch> {
     type ResultType;
-    fn fetch<'a>(archetypes: &'a [&'fetch Archetype]) -> Self::ResultType;
+    fn fetch<'a>(
+        archetypes: &'a [&'fetch Archetype],
+        change_tick: u64,
+    ) -> Result<Self::ResultType, TitanError>;
 }
 
 ///
@@ -71,6 +301,26 @@ pub struct ParameterFetchRead<T> {
 pub struct ParameterFetchWrite<T> {
     phantom: PhantomData<T>,
 }
+///
+/// ParameterFetch marker struct for optional Read.
+///
+pub struct ParameterFetchOptionRead<T> {
+    phantom: PhantomData<T>,
+}
+///
+/// ParameterFetch marker struct for optional Write.
+///
+pub struct ParameterFetchOptionWrite<T> {
+    phantom: PhantomData<T>,
+}
+///
+/// ParameterFetch marker struct for filters.
+///
+pub struct ParameterFetchFilter;
+///
+/// ParameterFetch marker struct for Entity.
+///
+pub struct ParameterFetchEntity;
 
 ///
 /// `ParameterFetch` implementation for Read.
@@ -80,12 +330,15 @@ where
     T: 'static + ComponentMeta,
 {
     type ResultType = Vec<RwLockReadGuard<'fetch, Vec<T>>>;
-    fn fetch<'a>(archetypes: &'a [&'fetch Archetype]) -> Self::ResultType {
+    fn fetch<'a>(
+        archetypes: &'a [&'fetch Archetype],
+        _change_tick: u64,
+    ) -> Result<Self::ResultType, TitanError> {
         let mut locks = Vec::new();
         for archetype in archetypes {
-            locks.push(archetype.get_component_vec_lock::<T>());
+            locks.push(archetype.get_component_vec_lock::<T>()?);
         }
-        locks
+        Ok(locks)
     }
 }
 ///
@@ -95,13 +348,162 @@ impl<'fetch, T> ParameterFetch<'fetch> for ParameterFetchWrite<T>
 where
     T: 'static + ComponentMeta,
 {
-    type ResultType = Vec<RwLockWriteGuard<'fetch, Vec<T>>>;
-    fn fetch<'a>(archetypes: &'a [&'fetch Archetype]) -> Self::ResultType {
+    type ResultType = Vec<ComponentVecWrite<'fetch, T>>;
+    fn fetch<'a>(
+        archetypes: &'a [&'fetch Archetype],
+        change_tick: u64,
+    ) -> Result<Self::ResultType, TitanError> {
         let mut locks = Vec::new();
         for archetype in archetypes {
-            locks.push(archetype.get_component_vec_lock_mut::<T>());
+            locks.push(ComponentVecWrite::new(archetype, change_tick)?);
         }
-        locks
+        Ok(locks)
+    }
+}
+///
+/// `ParameterFetch` implementation for optional Read. Each archetype lacking the component
+/// contributes its entity count instead of a lock.
+///
+impl<'fetch, T> ParameterFetch<'fetch> for ParameterFetchOptionRead<T>
+where
+    T: 'static + ComponentMeta,
+{
+    type ResultType = Vec<(usize, Option<RwLockReadGuard<'fetch, Vec<T>>>)>;
+    fn fetch<'a>(
+        archetypes: &'a [&'fetch Archetype],
+        _change_tick: u64,
+    ) -> Result<Self::ResultType, TitanError> {
+        let mut locks = Vec::new();
+        for archetype in archetypes {
+            let lock = if archetype.has_component::<T>() {
+                Some(archetype.get_component_vec_lock::<T>()?)
+            } else {
+                None
+            };
+            locks.push((archetype.get_entity_count(), lock));
+        }
+        Ok(locks)
+    }
+}
+///
+/// `ParameterFetch` implementation for optional Write. Each archetype lacking the component
+/// contributes its entity count instead of a lock.
+///
+impl<'fetch, T> ParameterFetch<'fetch> for ParameterFetchOptionWrite<T>
+where
+    T: 'static + ComponentMeta,
+{
+    type ResultType = Vec<(usize, Option<ComponentVecWrite<'fetch, T>>)>;
+    fn fetch<'a>(
+        archetypes: &'a [&'fetch Archetype],
+        change_tick: u64,
+    ) -> Result<Self::ResultType, TitanError> {
+        let mut locks = Vec::new();
+        for archetype in archetypes {
+            let lock = if archetype.has_component::<T>() {
+                Some(ComponentVecWrite::new(archetype, change_tick)?)
+            } else {
+                None
+            };
+            locks.push((archetype.get_entity_count(), lock));
+        }
+        Ok(locks)
+    }
+}
+///
+/// `ParameterFetch` implementation for filters.
+///
+impl<'fetch> ParameterFetch<'fetch> for ParameterFetchFilter {
+    type ResultType = FilterResult;
+    fn fetch<'a>(
+        archetypes: &'a [&'fetch Archetype],
+        _change_tick: u64,
+    ) -> Result<Self::ResultType, TitanError> {
+        Ok(FilterResult {
+            entity_count: archetypes
+                .iter()
+                .map(|archetype| archetype.get_entity_count())
+                .sum(),
+        })
+    }
+}
+///
+/// `ParameterFetch` implementation for Entity.
+///
+impl<'fetch> ParameterFetch<'fetch> for ParameterFetchEntity {
+    type ResultType = Vec<&'fetch [Entity]>;
+    fn fetch<'a>(
+        archetypes: &'a [&'fetch Archetype],
+        _change_tick: u64,
+    ) -> Result<Self::ResultType, TitanError> {
+        Ok(archetypes
+            .iter()
+            .map(|archetype| archetype.get_entity_ids())
+            .collect())
+    }
+}
+
+///
+/// Write lock of the component vec of an archetype, together with the ticks recording which of
+/// its rows are written to.
+///
+pub struct ComponentVecWrite<'fetch, T> {
+    lock: RwLockWriteGuard<'fetch, Vec<T>>,
+    ticks: Option<&'fetch ComponentTicks>,
+    change_tick: u64,
+}
+impl<'fetch, T: 'static + ComponentMeta> ComponentVecWrite<'fetch, T> {
+    fn new(archetype: &'fetch Archetype, change_tick: u64) -> Result<Self, TitanError> {
+        Ok(ComponentVecWrite {
+            lock: archetype.get_component_vec_lock_mut::<T>()?,
+            ticks: archetype.get_component_ticks::<T>(),
+            change_tick,
+        })
+    }
+}
+impl<'fetch, T> ComponentVecWrite<'fetch, T> {
+    fn iter_mut(&mut self) -> impl Iterator<Item = Mut<'_, T>> {
+        let ticks = self.ticks;
+        let change_tick = self.change_tick;
+        self.lock
+            .iter_mut()
+            .enumerate()
+            .map(move |(row, component)| Mut {
+                component,
+                ticks,
+                row,
+                change_tick,
+            })
+    }
+}
+
+///
+/// Write access to a component yielded by a query. The component is recorded as changed when it
+/// is dereferenced mutably, so entities which are only read stay unchanged.
+///
+pub struct Mut<'a, T> {
+    component: &'a mut T,
+    ticks: Option<&'a ComponentTicks>,
+    row: usize,
+    change_tick: u64,
+}
+impl<'a, T: Debug> Debug for Mut<'a, T> {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.component.fmt(formatter)
+    }
+}
+impl<'a, T> Deref for Mut<'a, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        self.component
+    }
+}
+impl<'a, T> DerefMut for Mut<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        if let Some(ticks) = self.ticks {
+            ticks.set_changed(self.row, self.change_tick);
+        }
+        self.component
     }
 }
 
@@ -124,7 +526,7 @@ impl<'borrow, 'fetch: 'borrow, T: 'fetch> ResultIter<'borrow>
 {
     type IterType = impl Iterator<Item = &'borrow T>;
     fn result_iter(&'borrow mut self) -> Self::IterType {
-        <[_]>::iter(self).map(|guard| guard.iter()).flatten()
+        <[_]>::iter(self).flat_map(|guard| guard.iter())
     }
 }
 
@@ -132,21 +534,76 @@ impl<'borrow, 'fetch: 'borrow, T: 'fetch> ResultIter<'borrow>
 /// ResultIter implementation for Write
 ///
 impl<'borrow, 'fetch: 'borrow, T: 'fetch> ResultIter<'borrow>
-    for Vec<RwLockWriteGuard<'fetch, Vec<T>>>
+    for Vec<ComponentVecWrite<'fetch, T>>
+{
+    type IterType = impl Iterator<Item = Mut<'borrow, T>>;
+    fn result_iter(&'borrow mut self) -> Self::IterType {
+        <[_]>::iter_mut(self).flat_map(|write| write.iter_mut())
+    }
+}
+
+///
+/// ResultIter implementation for optional Read
+///
+impl<'borrow, 'fetch: 'borrow, T: 'fetch> ResultIter<'borrow>
+    for Vec<(usize, Option<RwLockReadGuard<'fetch, Vec<T>>>)>
 {
-    type IterType = impl Iterator<Item = &'borrow mut T>;
+    type IterType = impl Iterator<Item = Option<&'borrow T>>;
     fn result_iter(&'borrow mut self) -> Self::IterType {
-        <[_]>::iter_mut(self)
-            .map(|guard| guard.iter_mut())
-            .flatten()
+        <[_]>::iter(self).flat_map(|(entity_count, guard)| match guard {
+            Some(guard) => Either::Left(guard.iter().map(Some)),
+            None => Either::Right(iter::repeat_with(|| None).take(*entity_count)),
+        })
     }
 }
 
 ///
+/// ResultIter implementation for optional Write
+///
+impl<'borrow, 'fetch: 'borrow, T: 'fetch> ResultIter<'borrow>
+    for Vec<(usize, Option<ComponentVecWrite<'fetch, T>>)>
+{
+    type IterType = impl Iterator<Item = Option<Mut<'borrow, T>>>;
+    fn result_iter(&'borrow mut self) -> Self::IterType {
+        <[_]>::iter_mut(self).flat_map(|(entity_count, write)| match write {
+            Some(write) => Either::Left(write.iter_mut().map(Some)),
+            None => Either::Right(iter::repeat_with(|| None).take(*entity_count)),
+        })
+    }
+}
+
+///
+/// Result of filter parameters, only the number of entities to yield `()` for.
+///
+pub struct FilterResult {
+    entity_count: usize,
+}
+
+///
+/// ResultIter implementation for filters
 ///
-/// Macros for generating tuple size specific structs and implementations.
+impl<'borrow> ResultIter<'borrow> for FilterResult {
+    type IterType = iter::RepeatN<()>;
+    fn result_iter(&'borrow mut self) -> Self::IterType {
+        iter::repeat_n((), self.entity_count)
+    }
+}
+
 ///
+/// ResultIter implementation for Entity
 ///
+impl<'borrow, 'fetch: 'borrow> ResultIter<'borrow> for Vec<&'fetch [Entity]> {
+    type IterType = impl Iterator<Item = Entity>;
+    fn result_iter(&'borrow mut self) -> Self::IterType {
+        <[_]>::iter(self).flat_map(|entity_ids| entity_ids.iter().copied())
+    }
+}
+
+//
+//
+// Macros for generating tuple size specific structs and implementations.
+//
+//
 
 ///
 /// Implementations of `Query` for `Parameter` tuples.
@@ -156,15 +613,42 @@ macro_rules! query_impl {
         paste!{
             impl<'fetch, $($name),*> Query<'fetch> for ($($name),*,)
             where
-                $($name: 'static + Debug + Parameter + ComponentMeta),*,
+                $($name: Debug + Parameter),*,
             {
                 type ResultType = [<Result $count>]<'fetch, $($name),*>;
-                fn query(storage: &'fetch Storage) -> Self::ResultType {
-                    let archetypes = <($($name),*,)>::find_matching_archetypes(storage);
-                    $(let [<component_vec_locks_ $name:lower>] = <$name::ParameterFetch>::fetch(&archetypes[..]));*;
-                    [<Result $count>] {
+                fn query<QueryFilter: Filter>(
+                    storage: &'fetch Storage,
+                    last_run_tick: u64,
+                    change_tick: u64,
+                ) -> Result<Self::ResultType, TitanError> {
+                    let mut archetypes = <($($name),*,)>::find_matching_archetypes(storage);
+                    archetypes.retain(|archetype| QueryFilter::matches_archetype(archetype));
+
+                    // Find matching rows of each archetype if any parameter or the filter
+                    // filters rows
+                    let rows = if QueryFilter::filters_rows() $(|| <$name>::filters_rows())* {
+                        Some(
+                            archetypes
+                                .iter()
+                                .map(|archetype| {
+                                    let mut rows = vec![true; archetype.get_entity_count()];
+                                    QueryFilter::filter_rows(archetype, last_run_tick, &mut rows);
+                                    $(<$name>::filter_rows(archetype, last_run_tick, &mut rows));*;
+                                    rows
+                                })
+                                .collect::<Vec<_>>(),
+                        )
+                    } else {
+                        None
+                    };
+                    $(let [<component_vec_locks_ $name:lower>] = <$name::ParameterFetch>::fetch(&archetypes[..], change_tick)?);*;
+                    Ok([<Result $count>] {
+                        rows: rows.map(|rows| rows.concat()),
                         $([<$name:lower>]: [<component_vec_locks_ $name:lower>]),*,
-                    }
+                    })
+                }
+                fn add_access(access: &mut Access) {
+                    $(<$name>::add_access(access));*;
                 }
             }
         }
@@ -179,24 +663,67 @@ query_impl!(6, A, B, C, D, E, F);
 query_impl!(7, A, B, C, D, E, F, G);
 query_impl!(8, A, B, C, D, E, F, G, H);
 
+///
+/// Implementations of `Filter` for `FilterParameter` tuples and of `ArchetypeFilter` for
+/// `ArchetypeFilterParameter` tuples.
+///
+macro_rules! filter_impl {
+    ($($name:ident),*) => {
+        impl<$($name),*> Filter for ($($name),*,)
+        where
+            $($name: FilterParameter),*,
+        {
+            fn matches_archetype(archetype: &Archetype) -> bool {
+                $(<$name>::matches_archetype(archetype))&&*
+            }
+            fn filters_rows() -> bool {
+                $(<$name>::filters_rows())||*
+            }
+            fn filter_rows(archetype: &Archetype, last_run_tick: u64, rows: &mut [bool]) {
+                $(<$name>::filter_rows(archetype, last_run_tick, rows));*;
+            }
+            fn add_access(access: &mut Access) {
+                $(<$name>::add_access(access));*;
+            }
+        }
+        impl<$($name),*> ArchetypeFilter for ($($name),*,)
+        where
+            $($name: ArchetypeFilterParameter),*,
+        {
+        }
+    };
+}
+filter_impl!(A);
+filter_impl!(A, B);
+filter_impl!(A, B, C);
+filter_impl!(A, B, C, D);
+filter_impl!(A, B, C, D, E);
+filter_impl!(A, B, C, D, E, F);
+filter_impl!(A, B, C, D, E, F, G);
+filter_impl!(A, B, C, D, E, F, G, H);
+
 ///
 /// Archetype matching trait and implementations
 ///
-trait MatchArchetype<'a> {
-    fn find_matching_archetypes(storage: &Storage) -> Vec<&Archetype>;
+pub trait MatchArchetype {
+    fn matches_archetype(archetype: &Archetype) -> bool;
+    fn find_matching_archetypes(storage: &Storage) -> Vec<&Archetype> {
+        storage
+            .archetype_by_bundle_kind
+            .values()
+            .filter(|archetype| Self::matches_archetype(archetype))
+            .collect()
+    }
 }
 macro_rules! match_archetype_impl {
     ($($name:ident),*) => {
         paste!{
-            impl<'a, $($name),*> MatchArchetype<'a> for ($($name),*,)
+            impl<$($name),*> MatchArchetype for ($($name),*,)
             where
-                $($name: 'static + Debug + Parameter + ComponentMeta),*,
+                $($name: Debug + Parameter),*,
             {
-                fn find_matching_archetypes(storage: &Storage) -> Vec<&Archetype> {
-                    storage
-                        .archetype_by_bundle_kind
-                        .values()
-                        .filter(|archetype| $(archetype.has_component::<$name>())&&*).collect()
+                fn matches_archetype(archetype: &Archetype) -> bool {
+                    $(<$name>::matches_archetype(archetype))&&*
                 }
             }
         }
@@ -221,6 +748,7 @@ macro_rules! result_struct {
             where
                 $($name: Parameter),*
             {
+                rows: Option<Vec<bool>>,
                 $([<$name:lower>]: <$name::ParameterFetch as ParameterFetch<'fetch>>::ResultType),*
             }
         }
@@ -248,13 +776,19 @@ macro_rules! result_iter_impl {
         paste!{
             impl<'borrow, 'fetch, $($name),*> ResultIter<'borrow> for [<Result $count>]<'fetch, $($name),*>
             where
-                $($name: Parameter + ComponentMeta),*,
+                $($name: Parameter),*,
                 $(<$name::ParameterFetch as ParameterFetch<'fetch>>::ResultType: ResultIter<'borrow>),*,
             {
                 #[allow(unused_parens)]
                 type IterType = impl Iterator<Item = ($(iter_return_parameter!($name)),*)>;
+                #[allow(unused_parens)]
                 fn result_iter(&'borrow mut self) -> Self::IterType {
-                    izip!($(self.[<$name:lower>].result_iter()),*)
+                    let rows = match &self.rows {
+                        Some(rows) => Either::Left(rows.iter().copied()),
+                        None => Either::Right(iter::repeat(true)),
+                    };
+                    izip!(rows, $(self.[<$name:lower>].result_iter()),*)
+                        .filter_map(|(matches, $([<$name:lower>]),*)| matches.then_some(($([<$name:lower>]),*)))
                 }
             }
         }
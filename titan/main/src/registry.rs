@@ -1,208 +1,766 @@
 use crate::{
-    bundle::BundleKind,
-    storage::{Archetype, Storage},
-    ComponentKind, ComponentMeta,
+    bundle::{Bundle, BundleKind},
+    entity::Entity,
+    resource::Resources,
+    serialization::UnknownComponentPolicy,
+    storage::{
+        new_component_vec, Archetype, BoxedComponent, ComponentVec, NewComponentVecFn, Storage,
+    },
+    ComponentKind, ComponentMeta, TitanError,
 };
-use paste::paste;
-use serde::{de::DeserializeOwned, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_json::Map;
 use serde_json::Value;
 use std::{
-    any::Any,
-    collections::{HashMap, HashSet},
+    any::{Any, TypeId},
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::{Arc, RwLock},
 };
 
-type SerializeFn = Box<dyn Fn(&dyn Any) -> Value>;
-type DeserializeFn = Box<dyn Fn(&str) -> Box<dyn Any>>;
-type ArchetypeEntitySerializeFn = Box<dyn Fn(usize, &Archetype, &BundleKind) -> Value>;
-type ArchetypeEntityDeserializeFn = Box<dyn Fn(&Value, &mut Storage)>;
+type SerializeFn = Arc<dyn Fn(&Archetype, usize) -> Result<Value, TitanError> + Send + Sync>;
+type DeserializeFn = Arc<dyn Fn(&Value) -> Result<Box<dyn Any>, TitanError> + Send + Sync>;
+type ColumnSerializeFn =
+    Arc<dyn Fn(&Archetype, Option<&[usize]>) -> Result<Value, TitanError> + Send + Sync>;
+type ColumnDeserializeFn =
+    Arc<dyn Fn(&Value) -> Result<Box<dyn ComponentVec>, TitanError> + Send + Sync>;
+type BinarySerializeFn =
+    Arc<dyn Fn(&Archetype, Option<&[usize]>) -> Result<Vec<u8>, TitanError> + Send + Sync>;
+type MapEntitiesFn = Arc<dyn Fn(&mut Archetype, &HashMap<Entity, Entity>) + Send + Sync>;
+///
+/// Migration of the serialized value of a component from one save version to the next.
+///
+pub type MigrationFn = fn(Value) -> Value;
+type BinaryDeserializeFn =
+    Arc<dyn Fn(&[u8]) -> Result<Box<dyn ComponentVec>, TitanError> + Send + Sync>;
+type ResourceSerializeFn =
+    Arc<dyn Fn(&Resources) -> Result<Option<Value>, TitanError> + Send + Sync>;
+type ResourceDeserializeFn =
+    Arc<dyn Fn(&Value, &mut Resources) -> Result<(), TitanError> + Send + Sync>;
+type BinaryResourceSerializeFn =
+    Arc<dyn Fn(&Resources) -> Result<Option<Vec<u8>>, TitanError> + Send + Sync>;
+type BinaryResourceDeserializeFn =
+    Arc<dyn Fn(&[u8], &mut Resources) -> Result<(), TitanError> + Send + Sync>;
+pub(crate) type ArchetypeEntitySerializeFn =
+    Box<dyn Fn(usize, &Archetype, &BundleKind) -> Result<Value, TitanError> + Send + Sync>;
+pub(crate) type ArchetypeEntityDeserializeFn =
+    Box<dyn Fn(&Value, &mut Storage) -> Result<(), TitanError> + Send + Sync>;
 
 pub struct Registry {
     kinds: HashSet<ComponentKind>,
+    kind_to_type_id: HashMap<ComponentKind, TypeId>,
+    transient_kinds: HashSet<ComponentKind>,
     kind_to_serializer: HashMap<ComponentKind, SerializeFn>,
     kind_to_deserializer: HashMap<ComponentKind, DeserializeFn>,
+    kind_to_new_component_vec_fn: HashMap<ComponentKind, NewComponentVecFn>,
+    kind_to_column_serializer: HashMap<ComponentKind, ColumnSerializeFn>,
+    kind_to_column_deserializer: HashMap<ComponentKind, ColumnDeserializeFn>,
+    kind_to_binary_serializer: HashMap<ComponentKind, BinarySerializeFn>,
+    kind_to_map_entities_fn: HashMap<ComponentKind, MapEntitiesFn>,
+    kind_to_migrations: HashMap<ComponentKind, Vec<(u32, MigrationFn)>>,
+    save_version: u32,
+    unknown_component_policy: UnknownComponentPolicy,
+    kind_to_binary_deserializer: HashMap<ComponentKind, BinaryDeserializeFn>,
     bundle_kinds: HashSet<BundleKind>,
     bundle_kind_to_archetype_entity_serialize_fn: HashMap<BundleKind, ArchetypeEntitySerializeFn>,
-    pub bundle_kind_to_archetype_entity_deserialize_fn:
+    bundle_kind_to_archetype_entity_deserialize_fn:
         HashMap<BundleKind, ArchetypeEntityDeserializeFn>,
+    ///
+    /// Bundle types and kinds whose automatic registration has been attempted, successful or not,
+    /// so spawning and migrating entities does not retry it. Cleared when a component kind is
+    /// registered, since registration may then succeed.
+    ///
+    auto_registration_attempted_bundle_types: HashSet<TypeId>,
+    auto_registration_attempted_bundle_kinds: HashSet<BundleKind>,
+    resource_kind_to_type_id: HashMap<String, TypeId>,
+    resource_kind_to_serializer: BTreeMap<String, ResourceSerializeFn>,
+    resource_kind_to_deserializer: HashMap<String, ResourceDeserializeFn>,
+    resource_kind_to_binary_serializer: BTreeMap<String, BinaryResourceSerializeFn>,
+    resource_kind_to_binary_deserializer: HashMap<String, BinaryResourceDeserializeFn>,
 }
 impl Registry {
     pub(crate) fn new() -> Self {
         Self {
             kinds: HashSet::new(),
+            kind_to_type_id: HashMap::new(),
+            transient_kinds: HashSet::new(),
             kind_to_serializer: HashMap::new(),
             kind_to_deserializer: HashMap::new(),
+            kind_to_new_component_vec_fn: HashMap::new(),
+            kind_to_column_serializer: HashMap::new(),
+            kind_to_column_deserializer: HashMap::new(),
+            kind_to_binary_serializer: HashMap::new(),
+            kind_to_map_entities_fn: HashMap::new(),
+            kind_to_migrations: HashMap::new(),
+            save_version: 0,
+            unknown_component_policy: UnknownComponentPolicy::Error,
+            kind_to_binary_deserializer: HashMap::new(),
             bundle_kinds: HashSet::new(),
             bundle_kind_to_archetype_entity_serialize_fn: HashMap::new(),
             bundle_kind_to_archetype_entity_deserialize_fn: HashMap::new(),
+            auto_registration_attempted_bundle_types: HashSet::new(),
+            auto_registration_attempted_bundle_kinds: HashSet::new(),
+            resource_kind_to_type_id: HashMap::new(),
+            resource_kind_to_serializer: BTreeMap::new(),
+            resource_kind_to_deserializer: HashMap::new(),
+            resource_kind_to_binary_serializer: BTreeMap::new(),
+            resource_kind_to_binary_deserializer: HashMap::new(),
         }
     }
-    pub(crate) fn register_component<T: RegisterComponent>(&mut self) {
-        T::register(self);
+    pub(crate) fn register_component<T: RegisterComponent>(&mut self) -> Result<(), TitanError> {
+        let kind_count = self.kinds.len();
+        T::register(self)?;
+        if self.kinds.len() != kind_count {
+            self.clear_auto_registration_attempts();
+        }
+        Ok(())
+    }
+    pub(crate) fn register_archetype<T: RegisterArchetype>(&mut self) -> Result<(), TitanError> {
+        T::register(self)
+    }
+    pub(crate) fn has_component_kind(&self, component_kind: &ComponentKind) -> bool {
+        self.kinds.contains(component_kind)
+    }
+    ///
+    /// Register a component kind which is never saved.
+    ///
+    pub(crate) fn register_transient_component_kind(&mut self, component_kind: ComponentKind) {
+        if self.transient_kinds.insert(component_kind) {
+            self.clear_auto_registration_attempts();
+        }
+    }
+    pub(crate) fn is_transient_component_kind(&self, component_kind: &ComponentKind) -> bool {
+        self.transient_kinds.contains(component_kind)
+    }
+    pub(crate) fn set_unknown_component_policy(
+        &mut self,
+        unknown_component_policy: UnknownComponentPolicy,
+    ) {
+        self.unknown_component_policy = unknown_component_policy;
+    }
+    pub(crate) fn get_unknown_component_policy(&self) -> UnknownComponentPolicy {
+        self.unknown_component_policy
+    }
+    pub(crate) fn set_save_version(&mut self, save_version: u32) {
+        self.save_version = save_version;
+    }
+    pub(crate) fn get_save_version(&self) -> u32 {
+        self.save_version
+    }
+    ///
+    /// Register a migration upgrading serialized values of component `T` from `from_version` to
+    /// the next version. Migrations registered for the same version are applied in order of
+    /// registration.
+    ///
+    pub(crate) fn register_migration<T: ComponentMeta>(
+        &mut self,
+        from_version: u32,
+        migration: MigrationFn,
+    ) {
+        let migrations = self
+            .kind_to_migrations
+            .entry(T::get_component_kind())
+            .or_default();
+        migrations.push((from_version, migration));
+        migrations.sort_by_key(|(from_version, _)| *from_version);
+    }
+    ///
+    /// Check that data saved at the given version can be loaded, which is not the case for saves
+    /// newer than the current save version.
+    ///
+    pub(crate) fn check_save_version(&self, save_version: u32) -> Result<(), TitanError> {
+        if save_version > self.save_version {
+            return Err(TitanError::UnsupportedSaveVersion(save_version));
+        }
+        Ok(())
+    }
+    fn get_pending_migrations(
+        &self,
+        component_kind: &ComponentKind,
+        save_version: u32,
+    ) -> impl Iterator<Item = &MigrationFn> {
+        self.kind_to_migrations
+            .get(component_kind)
+            .into_iter()
+            .flatten()
+            .filter(move |(from_version, _)| {
+                *from_version >= save_version && *from_version < self.save_version
+            })
+            .map(|(_, migration)| migration)
+    }
+    ///
+    /// Check if any migration must be applied to components of the given kind saved at the given
+    /// version.
+    ///
+    pub(crate) fn has_pending_migrations(
+        &self,
+        component_kind: &ComponentKind,
+        save_version: u32,
+    ) -> bool {
+        self.get_pending_migrations(component_kind, save_version)
+            .next()
+            .is_some()
+    }
+    ///
+    /// Upgrade the serialized value of a component saved at the given version to the current
+    /// save version.
+    ///
+    pub(crate) fn migrate_component(
+        &self,
+        component_kind: &ComponentKind,
+        component_value: Value,
+        save_version: u32,
+    ) -> Value {
+        self.get_pending_migrations(component_kind, save_version)
+            .fold(component_value, |component_value, migration| {
+                (migration)(component_value)
+            })
+    }
+    ///
+    /// Upgrade every component of a serialized entity object saved at the given version to the
+    /// current save version.
+    ///
+    pub(crate) fn migrate_entity(&self, entity_value: Value, save_version: u32) -> Value {
+        if save_version == self.save_version {
+            return entity_value;
+        }
+        match entity_value {
+            Value::Object(mut entity_object) => {
+                for (key, component_value) in entity_object.iter_mut() {
+                    if key == "bundle_kind" || key == "entity" {
+                        continue;
+                    }
+                    *component_value = self.migrate_component(
+                        &ComponentKind(key.clone()),
+                        component_value.take(),
+                        save_version,
+                    );
+                }
+                Value::Object(entity_object)
+            }
+            entity_value => entity_value,
+        }
+    }
+    ///
+    /// Register the archetype holding exactly the given component kinds, composing its serialize
+    /// and deserialize functions from those of its components.
+    ///
+    /// Transient components are left out, so the archetype is registered as the archetype of its
+    /// saved components.
+    ///
+    pub(crate) fn register_bundle_kind(
+        &mut self,
+        component_kinds: &[ComponentKind],
+    ) -> Result<(), TitanError> {
+        let component_kinds: Vec<_> = component_kinds
+            .iter()
+            .filter(|component_kind| !self.is_transient_component_kind(component_kind))
+            .cloned()
+            .collect();
+        let component_kinds = component_kinds.as_slice();
+        let bundle_kind = BundleKind::from_component_kinds(component_kinds);
+        if self.bundle_kinds.contains(&bundle_kind) {
+            return Ok(());
+        }
+        let archetype_entity_serialize_fn =
+            self.build_archetype_entity_serialize_fn(component_kinds)?;
+        let archetype_entity_deserialize_fn =
+            self.build_archetype_entity_deserialize_fn(component_kinds)?;
+        self.bundle_kinds.insert(bundle_kind.clone());
+        self.bundle_kind_to_archetype_entity_serialize_fn
+            .insert(bundle_kind.clone(), archetype_entity_serialize_fn);
+        self.bundle_kind_to_archetype_entity_deserialize_fn
+            .insert(bundle_kind, archetype_entity_deserialize_fn);
+        Ok(())
+    }
+    ///
+    /// Register the archetype of a spawned bundle if all of its components are registered.
+    ///
+    pub(crate) fn auto_register_bundle<T: Bundle + 'static>(&mut self) {
+        let type_id = TypeId::of::<T>();
+        if self
+            .auto_registration_attempted_bundle_types
+            .contains(&type_id)
+        {
+            return;
+        }
+        for component_kind in T::get_transient_component_kinds() {
+            self.register_transient_component_kind(component_kind);
+        }
+
+        // Components which are not registered are simply not serializable
+        let _ = self.register_bundle_kind(&T::get_component_kinds());
+        self.auto_registration_attempted_bundle_types
+            .insert(type_id);
+    }
+    ///
+    /// Register the archetype of the given bundle kind if all of its components are registered.
+    /// The component kinds are only requested if registration has not been attempted yet.
+    ///
+    pub(crate) fn auto_register_bundle_kind(
+        &mut self,
+        bundle_kind: &BundleKind,
+        get_component_kinds: impl FnOnce() -> Vec<ComponentKind>,
+    ) {
+        if self
+            .auto_registration_attempted_bundle_kinds
+            .contains(bundle_kind)
+        {
+            return;
+        }
+        let _ = self.register_bundle_kind(&get_component_kinds());
+        self.auto_registration_attempted_bundle_kinds
+            .insert(bundle_kind.clone());
     }
-    pub(crate) fn register_archetype<T: RegisterArchetype>(&mut self) {
-        T::register(self);
+    fn clear_auto_registration_attempts(&mut self) {
+        self.auto_registration_attempted_bundle_types.clear();
+        self.auto_registration_attempted_bundle_kinds.clear();
     }
     pub(crate) fn bundle_kind_to_archetype_entity_serialize_fn(
         &self,
         kind: BundleKind,
-    ) -> &ArchetypeEntitySerializeFn {
+    ) -> Result<&ArchetypeEntitySerializeFn, TitanError> {
         self.bundle_kind_to_archetype_entity_serialize_fn
             .get(&kind)
-            .expect(&format!(
-                "Could not get serialize_fn given bundle_kind {:?}. Are you sure the archetype has been registered?",
-                kind
-            ))
+            .ok_or(TitanError::UnknownBundleKind(kind))
     }
     pub(crate) fn bundle_kind_to_archetype_entity_deserialize_fn(
         &self,
         kind: BundleKind,
-    ) -> &ArchetypeEntityDeserializeFn {
+    ) -> Result<&ArchetypeEntityDeserializeFn, TitanError> {
         self.bundle_kind_to_archetype_entity_deserialize_fn
             .get(&kind)
-            .expect(&format!(
-                "Could not get deserialize_fn given bundle_kind {:?}. Are you sure the archetype has been registered?",
-                kind,
-            ))
+            .ok_or(TitanError::UnknownBundleKind(kind))
+    }
+    ///
+    /// Encode the whole component vec of the given kind in the archetype as a JSON array, in the
+    /// order of `rows` if given.
+    ///
+    pub(crate) fn serialize_component_vec_json(
+        &self,
+        component_kind: &ComponentKind,
+        archetype: &Archetype,
+        rows: Option<&[usize]>,
+    ) -> Result<Value, TitanError> {
+        let column_serialize_fn = self
+            .kind_to_column_serializer
+            .get(component_kind)
+            .ok_or_else(|| TitanError::UnknownComponentKind(component_kind.clone()))?;
+        (column_serialize_fn)(archetype, rows)
+    }
+    ///
+    /// Decode a whole component vec of the given kind from a JSON array.
+    ///
+    pub(crate) fn deserialize_component_vec_json(
+        &self,
+        component_kind: &ComponentKind,
+        column_value: &Value,
+    ) -> Result<Box<dyn ComponentVec>, TitanError> {
+        let column_deserialize_fn = self
+            .kind_to_column_deserializer
+            .get(component_kind)
+            .ok_or_else(|| TitanError::UnknownComponentKind(component_kind.clone()))?;
+        (column_deserialize_fn)(column_value)
+    }
+    ///
+    /// Encode the whole component vec of the given kind in the archetype as binary, in the order
+    /// of `rows` if given.
+    ///
+    pub(crate) fn serialize_component_vec_binary(
+        &self,
+        component_kind: &ComponentKind,
+        archetype: &Archetype,
+        rows: Option<&[usize]>,
+    ) -> Result<Vec<u8>, TitanError> {
+        let binary_serialize_fn = self
+            .kind_to_binary_serializer
+            .get(component_kind)
+            .ok_or_else(|| TitanError::UnknownComponentKind(component_kind.clone()))?;
+        (binary_serialize_fn)(archetype, rows)
+    }
+    ///
+    /// Decode a whole component vec of the given kind from binary.
+    ///
+    pub(crate) fn deserialize_component_vec_binary(
+        &self,
+        component_kind: &ComponentKind,
+        bytes: &[u8],
+    ) -> Result<Box<dyn ComponentVec>, TitanError> {
+        let binary_deserialize_fn = self
+            .kind_to_binary_deserializer
+            .get(component_kind)
+            .ok_or_else(|| TitanError::UnknownComponentKind(component_kind.clone()))?;
+        (binary_deserialize_fn)(bytes)
+    }
+    ///
+    /// Register the resource `R` to be saved under `resource_kind`. A resource kind may only be
+    /// used by a single type.
+    ///
+    pub(crate) fn register_resource<R>(&mut self, resource_kind: &str) -> Result<(), TitanError>
+    where
+        R: Serialize + DeserializeOwned + Send + Sync + 'static,
+    {
+        let type_id = TypeId::of::<R>();
+        match self.resource_kind_to_type_id.get(resource_kind) {
+            Some(registered_type_id) if *registered_type_id != type_id => {
+                return Err(TitanError::ResourceKindCollision(resource_kind.to_string()));
+            }
+            Some(_) => return Ok(()),
+            None => {}
+        }
+        let resource_kind = resource_kind.to_string();
+        self.resource_kind_to_type_id
+            .insert(resource_kind.clone(), type_id);
+
+        // Register SerializeFn and DeserializeFn
+        let resource_serialize_fn = |resources: &Resources| match resources.get::<R>()? {
+            Some(resource) => serde_json::to_value(&*resource)
+                .map(Some)
+                .map_err(|error| TitanError::SerializationFailed(error.to_string())),
+            None => Ok(None),
+        };
+        self.resource_kind_to_serializer
+            .insert(resource_kind.clone(), Arc::new(resource_serialize_fn));
+        let resource_deserialize_fn = |resource_value: &Value, resources: &mut Resources| {
+            let resource = R::deserialize(resource_value)
+                .map_err(|error| TitanError::MalformedSaveData(error.to_string()))?;
+            resources.insert(resource);
+            Ok(())
+        };
+        self.resource_kind_to_deserializer
+            .insert(resource_kind.clone(), Arc::new(resource_deserialize_fn));
+
+        // Register binary SerializeFn and DeserializeFn
+        let resource_binary_serialize_fn = |resources: &Resources| match resources.get::<R>()? {
+            Some(resource) => bincode::serialize(&*resource)
+                .map(Some)
+                .map_err(|error| TitanError::SerializationFailed(error.to_string())),
+            None => Ok(None),
+        };
+        self.resource_kind_to_binary_serializer.insert(
+            resource_kind.clone(),
+            Arc::new(resource_binary_serialize_fn),
+        );
+        let resource_binary_deserialize_fn = |bytes: &[u8], resources: &mut Resources| {
+            let resource = bincode::deserialize::<R>(bytes)
+                .map_err(|error| TitanError::MalformedSaveData(error.to_string()))?;
+            resources.insert(resource);
+            Ok(())
+        };
+        self.resource_kind_to_binary_deserializer
+            .insert(resource_kind, Arc::new(resource_binary_deserialize_fn));
+        Ok(())
+    }
+    ///
+    /// Encode every registered resource which is present as JSON, by resource kind.
+    ///
+    pub(crate) fn serialize_resources_json(
+        &self,
+        resources: &Resources,
+    ) -> Result<BTreeMap<String, Value>, TitanError> {
+        let mut resource_values = BTreeMap::new();
+        for (resource_kind, resource_serialize_fn) in self.resource_kind_to_serializer.iter() {
+            if let Some(resource_value) = (resource_serialize_fn)(resources)? {
+                resource_values.insert(resource_kind.clone(), resource_value);
+            }
+        }
+        Ok(resource_values)
+    }
+    ///
+    /// Decode resources from JSON by resource kind, inserting them into `resources`.
+    ///
+    pub(crate) fn deserialize_resources_json(
+        &self,
+        resource_values: impl IntoIterator<Item = (String, Value)>,
+        resources: &mut Resources,
+    ) -> Result<(), TitanError> {
+        for (resource_kind, resource_value) in resource_values {
+            match self.resource_kind_to_deserializer.get(&resource_kind) {
+                Some(resource_deserialize_fn) => {
+                    (resource_deserialize_fn)(&resource_value, resources)?
+                }
+                None => self.check_unknown_resource_kind(resource_kind)?,
+            }
+        }
+        Ok(())
+    }
+    ///
+    /// Encode every registered resource which is present as binary, by resource kind.
+    ///
+    pub(crate) fn serialize_resources_binary(
+        &self,
+        resources: &Resources,
+    ) -> Result<BTreeMap<String, Vec<u8>>, TitanError> {
+        let mut resource_bytes = BTreeMap::new();
+        for (resource_kind, resource_binary_serialize_fn) in
+            self.resource_kind_to_binary_serializer.iter()
+        {
+            if let Some(bytes) = (resource_binary_serialize_fn)(resources)? {
+                resource_bytes.insert(resource_kind.clone(), bytes);
+            }
+        }
+        Ok(resource_bytes)
+    }
+    ///
+    /// Decode resources from binary by resource kind, inserting them into `resources`.
+    ///
+    pub(crate) fn deserialize_resources_binary(
+        &self,
+        resource_bytes: impl IntoIterator<Item = (String, Vec<u8>)>,
+        resources: &mut Resources,
+    ) -> Result<(), TitanError> {
+        for (resource_kind, bytes) in resource_bytes {
+            match self
+                .resource_kind_to_binary_deserializer
+                .get(&resource_kind)
+            {
+                Some(resource_binary_deserialize_fn) => {
+                    (resource_binary_deserialize_fn)(&bytes, resources)?
+                }
+                None => self.check_unknown_resource_kind(resource_kind)?,
+            }
+        }
+        Ok(())
+    }
+    ///
+    /// Unknown resources are skipped, unless loading fails on unknown components.
+    ///
+    fn check_unknown_resource_kind(&self, resource_kind: String) -> Result<(), TitanError> {
+        match self.unknown_component_policy {
+            UnknownComponentPolicy::Error => Err(TitanError::UnknownResourceKind(resource_kind)),
+            UnknownComponentPolicy::Drop | UnknownComponentPolicy::Preserve => Ok(()),
+        }
+    }
+    ///
+    /// Remap the entity references held by every component of the archetype.
+    ///
+    pub(crate) fn map_entities(
+        &self,
+        archetype: &mut Archetype,
+        entity_map: &HashMap<Entity, Entity>,
+    ) {
+        for component_kind in archetype.get_component_kinds() {
+            if let Some(map_entities_fn) = self.kind_to_map_entities_fn.get(&component_kind) {
+                (map_entities_fn)(archetype, entity_map);
+            }
+        }
+    }
+    pub(crate) fn build_archetype_entity_serialize_fn(
+        &self,
+        component_kinds: &[ComponentKind],
+    ) -> Result<ArchetypeEntitySerializeFn, TitanError> {
+        let mut serialize_fns = Vec::new();
+        for component_kind in component_kinds {
+            let serialize_fn = self
+                .kind_to_serializer
+                .get(component_kind)
+                .ok_or_else(|| TitanError::UnknownComponentKind(component_kind.clone()))?;
+            serialize_fns.push((component_kind.clone(), serialize_fn.clone()));
+        }
+
+        let archetype_entity_serialize_fn =
+            move |entity_index: usize, archetype: &Archetype, bundle_kind: &BundleKind| {
+                let entity = archetype.get_entity_id_at_index_unchecked(entity_index);
+
+                // Build entity object
+                let mut entity_object = Map::new();
+                entity_object.insert(
+                    "bundle_kind".to_string(),
+                    Value::from(bundle_kind.0.clone()),
+                );
+                entity_object.insert(
+                    "entity".to_string(),
+                    serde_json::to_value(entity)
+                        .map_err(|error| TitanError::SerializationFailed(error.to_string()))?,
+                );
+
+                // Serialize each component
+                for (component_kind, serialize_fn) in serialize_fns.iter() {
+                    entity_object.insert(
+                        component_kind.0.clone(),
+                        (serialize_fn)(archetype, entity_index)?,
+                    );
+                }
+
+                Ok(Value::from(entity_object))
+            };
+        Ok(Box::new(archetype_entity_serialize_fn))
+    }
+    pub(crate) fn build_archetype_entity_deserialize_fn(
+        &self,
+        component_kinds: &[ComponentKind],
+    ) -> Result<ArchetypeEntityDeserializeFn, TitanError> {
+        let bundle_kind = BundleKind::from_component_kinds(component_kinds);
+        let mut deserialize_fns = Vec::new();
+        for component_kind in component_kinds {
+            let deserialize_fn = self
+                .kind_to_deserializer
+                .get(component_kind)
+                .ok_or_else(|| TitanError::UnknownComponentKind(component_kind.clone()))?;
+            let new_component_vec_fn = self.kind_to_new_component_vec_fn[component_kind];
+            deserialize_fns.push((
+                component_kind.clone(),
+                deserialize_fn.clone(),
+                new_component_vec_fn,
+            ));
+        }
+
+        let archetype_entity_deserialize_fn = move |entity_value: &Value, storage: &mut Storage| {
+            let entity_object = entity_value
+                .as_object()
+                .ok_or_else(|| TitanError::malformed("Could not parse JSON value as object"))?;
+            let entity_value = entity_object
+                .get(&"entity".to_string())
+                .ok_or_else(|| TitanError::malformed("Could not get JSON entity"))?;
+            let entity = Entity::deserialize(entity_value)
+                .map_err(|error| TitanError::MalformedSaveData(error.to_string()))?;
+
+            // Deserialize each component
+            let mut components = Vec::new();
+            for (component_kind, deserialize_fn, new_component_vec_fn) in deserialize_fns.iter() {
+                let component_value = entity_object
+                    .get(&component_kind.0)
+                    .ok_or_else(|| TitanError::malformed("Could not get JSON component"))?;
+                components.push(BoxedComponent {
+                    component_kind: component_kind.clone(),
+                    component: (deserialize_fn)(component_value)?,
+                    new_component_vec_fn: *new_component_vec_fn,
+                });
+            }
+
+            storage.spawn_boxed_with_entity_id(entity, bundle_kind.clone(), components)
+        };
+        Ok(Box::new(archetype_entity_deserialize_fn))
     }
 }
 
 pub trait RegisterComponent {
-    fn register(registry: &mut Registry);
+    fn register(registry: &mut Registry) -> Result<(), TitanError>;
 }
 
 impl<T> RegisterComponent for T
 where
     T: Serialize + DeserializeOwned + ComponentMeta + 'static,
 {
-    fn register(registry: &mut Registry) {
-        // Register TypeId and Kind
+    fn register(registry: &mut Registry) -> Result<(), TitanError> {
+        // Register TypeId and Kind, a kind may only be used by a single type
         let kind = T::get_component_kind();
+        let type_id = TypeId::of::<T>();
+        match registry.kind_to_type_id.get(&kind) {
+            Some(registered_type_id) if *registered_type_id != type_id => {
+                return Err(TitanError::ComponentKindCollision(kind));
+            }
+            Some(_) => return Ok(()),
+            None => {}
+        }
+        registry.kind_to_type_id.insert(kind.clone(), type_id);
         registry.kinds.insert(kind.clone());
 
         // Register SerializeFn
-        let kind_serialize_fn = |item: &dyn Any| {
-            let item = item
-                .downcast_ref::<T>()
-                .expect("Could not downcast item to T");
-            serde_json::to_value(item).expect("Could not serialize kind to value")
+        let kind_serialize_fn = |archetype: &Archetype, entity_index: usize| {
+            let component_vec = archetype.get_component_vec_lock::<T>()?;
+            serde_json::to_value(&component_vec[entity_index])
+                .map_err(|error| TitanError::SerializationFailed(error.to_string()))
         };
         registry
             .kind_to_serializer
-            .insert(kind.clone(), Box::new(kind_serialize_fn));
+            .insert(kind.clone(), Arc::new(kind_serialize_fn));
 
         // Register DeserializeFn
-        let kind_deserialize_fn = |item_serial: &str| {
-            Box::new(
-                serde_json::from_str::<T>(item_serial)
-                    .expect("Could not deserialize str given kind"),
-            ) as Box<dyn Any>
+        let kind_deserialize_fn = |component_value: &Value| {
+            let component = T::deserialize(component_value)
+                .map_err(|error| TitanError::MalformedSaveData(error.to_string()))?;
+            Ok(Box::new(component) as Box<dyn Any>)
         };
         registry
             .kind_to_deserializer
-            .insert(kind.clone(), Box::new(kind_deserialize_fn));
+            .insert(kind.clone(), Arc::new(kind_deserialize_fn));
+
+        // Register SerializeFn of whole component vec
+        let kind_column_serialize_fn = |archetype: &Archetype, rows: Option<&[usize]>| {
+            let component_vec = archetype.get_component_vec_lock::<T>()?;
+            match rows {
+                Some(rows) => serde_json::to_value(select_rows(&component_vec, rows)),
+                None => serde_json::to_value(&*component_vec),
+            }
+            .map_err(|error| TitanError::SerializationFailed(error.to_string()))
+        };
+        registry
+            .kind_to_column_serializer
+            .insert(kind.clone(), Arc::new(kind_column_serialize_fn));
+
+        // Register DeserializeFn of whole component vec
+        let kind_column_deserialize_fn = |column_value: &Value| {
+            let component_vec = Vec::<T>::deserialize(column_value)
+                .map_err(|error| TitanError::MalformedSaveData(error.to_string()))?;
+            Ok(Box::new(RwLock::new(component_vec)) as Box<dyn ComponentVec>)
+        };
+        registry
+            .kind_to_column_deserializer
+            .insert(kind.clone(), Arc::new(kind_column_deserialize_fn));
+
+        // Register binary SerializeFn of whole component vec
+        let kind_binary_serialize_fn = |archetype: &Archetype, rows: Option<&[usize]>| {
+            let component_vec = archetype.get_component_vec_lock::<T>()?;
+            match rows {
+                Some(rows) => bincode::serialize(&select_rows(&component_vec, rows)),
+                None => bincode::serialize(&*component_vec),
+            }
+            .map_err(|error| TitanError::SerializationFailed(error.to_string()))
+        };
+        registry
+            .kind_to_binary_serializer
+            .insert(kind.clone(), Arc::new(kind_binary_serialize_fn));
+
+        // Register binary DeserializeFn of whole component vec
+        let kind_binary_deserialize_fn = |bytes: &[u8]| {
+            let component_vec = bincode::deserialize::<Vec<T>>(bytes)
+                .map_err(|error| TitanError::MalformedSaveData(error.to_string()))?;
+            Ok(Box::new(RwLock::new(component_vec)) as Box<dyn ComponentVec>)
+        };
+        registry
+            .kind_to_binary_deserializer
+            .insert(kind.clone(), Arc::new(kind_binary_deserialize_fn));
+
+        // Register MapEntitiesFn if component holds entity references
+        if let Some(component_map_entities_fn) = T::get_map_entities_fn() {
+            let kind_map_entities_fn =
+                move |archetype: &mut Archetype, entity_map: &HashMap<Entity, Entity>| {
+                    for component in archetype.get_component_vec_mut::<T>() {
+                        (component_map_entities_fn)(component, entity_map);
+                    }
+                };
+            registry
+                .kind_to_map_entities_fn
+                .insert(kind.clone(), Arc::new(kind_map_entities_fn));
+        }
+
+        // Register component vec constructor
+        registry
+            .kind_to_new_component_vec_fn
+            .insert(kind, new_component_vec::<T>);
+        Ok(())
     }
 }
 
+///
+/// Get references to the components at the given rows, which serialize like a component vec.
+///
+fn select_rows<'a, T>(component_vec: &'a [T], rows: &[usize]) -> Vec<&'a T> {
+    rows.iter().map(|row| &component_vec[*row]).collect()
+}
+
 pub trait RegisterArchetype {
-    fn register(registry: &mut Registry);
+    fn register(registry: &mut Registry) -> Result<(), TitanError>;
 }
 
-macro_rules! register_archetype_impl {
-        (
-            $($name:ident),*
-        ) => {
-            impl<$($name),*> RegisterArchetype for ($($name),*,)
-            where
-                $(
-                    $name: Serialize + DeserializeOwned + ComponentMeta + 'static
-                 ),*,
-            {
-                #[allow(non_snake_case)]
-                fn register(registry: &mut Registry) {
-                    // Register Kind
-                    $(let paste!{[<kind_ $name>]} = <$name>::get_component_kind();)*
-                    let bundle_kind = BundleKind::from_component_kinds(&[$(paste!{[<kind_ $name>]}),*]);
-
-                    registry.bundle_kinds.insert(bundle_kind.clone());
-
-                    // Register SerializeFn
-                    let archetype_entity_serialize_fn =
-                        |entity_index: usize, archetype: &Archetype, bundle_kind: &BundleKind| {
-                            let entity_id =
-                                archetype.get_entity_id_at_index_unchecked(entity_index);
-
-                            // Serialize each component
-                            $(
-                                let paste!{[<component_ $name>]} = &archetype.get_component_vec_lock::<$name>()[entity_index];
-                                let paste!{[<component_ $name _value>]} =
-                                    serde_json::to_value(paste!{[<component_ $name>]}).unwrap();
-                             )*
-
-                            // Build entity object
-                            let mut entity_object = Map::new();
-                            entity_object.insert(
-                                "bundle_kind".to_string(),
-                                Value::from(bundle_kind.0.clone()),
-                            );
-                            entity_object.insert("entity_id".to_string(), Value::from(entity_id));
-
-                            $(
-                                entity_object.insert(stringify!{$name}.to_string(),
-                                    paste!{[<component_ $name _value>]});
-                             )*
-
-                            Value::from(entity_object)
-                        };
-                    registry
-                        .bundle_kind_to_archetype_entity_serialize_fn
-                        .insert(bundle_kind.clone(), Box::new(archetype_entity_serialize_fn));
-
-                    // Register DeserializeFn
-                    let archetype_entity_deserialize_fn =
-                        |entity_value: &Value, storage: &mut Storage| {
-                            let entity_object = entity_value
-                                .as_object()
-                                .expect("Could not parse JSON value as object");
-                            let entity_id = entity_object
-                                .get(&"entity_id".to_string())
-                                .expect("Could not get JSON entity_id")
-                                .as_u64()
-                                .expect("Could not parse JSON value as u64");
-
-                            $(
-                                let paste!{[<component_ $name _value>]} = entity_object
-                                    .get(&stringify!{$name}.to_string())
-                                    .expect("Could not get JSON component");
-                                let paste!{[<component_ $name>]} =
-                                    serde_json::from_value::<$name>(paste!{[<component_ $name _value>]}.clone())
-                                        .expect("Could not parse JSON value as component");
-                             )*
-
-                                let bundle = (
-                                    $(
-                                        paste!{[<component_ $name>]}
-                                     ),*,
-                                             );
-
-                            storage.spawn_with_entity_id(entity_id as usize, bundle);
-                        };
-                    registry
-                        .bundle_kind_to_archetype_entity_deserialize_fn
-                        .insert(
-                            bundle_kind.clone(),
-                            Box::new(archetype_entity_deserialize_fn),
-                        );
-                }
-            }
-        };
+impl<T> RegisterArchetype for T
+where
+    T: Bundle,
+{
+    fn register(registry: &mut Registry) -> Result<(), TitanError> {
+        for component_kind in T::get_transient_component_kinds() {
+            registry.register_transient_component_kind(component_kind);
+        }
+        registry.register_bundle_kind(&T::get_component_kinds())
     }
-
-register_archetype_impl! { A }
-register_archetype_impl! { A, B }
-register_archetype_impl! { A, B, C }
-register_archetype_impl! { A, B, C, D }
-register_archetype_impl! { A, B, C, D, E }
-register_archetype_impl! { A, B, C, D, E, F }
-register_archetype_impl! { A, B, C, D, E, F, G }
-register_archetype_impl! { A, B, C, D, E, F, G, H }
+}
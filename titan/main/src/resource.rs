@@ -0,0 +1,132 @@
+use crate::TitanError;
+use std::{
+    any::{type_name, Any, TypeId},
+    collections::HashMap,
+    ops::{Deref, DerefMut},
+    sync::{RwLock, RwLockReadGuard, RwLockWriteGuard, TryLockError},
+};
+
+///
+/// World wide values stored by type, at most one of each type. Each resource is guarded by its
+/// own lock, like component vecs.
+///
+#[derive(Default)]
+pub(crate) struct Resources {
+    resource_locks_by_type_id: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
+}
+impl Resources {
+    ///
+    /// Insert the resource, returning the replaced resource of the same type if any.
+    ///
+    pub(crate) fn insert<R: 'static + Send + Sync>(&mut self, resource: R) -> Option<R> {
+        self.resource_locks_by_type_id
+            .insert(TypeId::of::<R>(), Box::new(RwLock::new(resource)))
+            .map(unwrap_resource_lock)
+    }
+    pub(crate) fn remove<R: 'static + Send + Sync>(&mut self) -> Option<R> {
+        self.resource_locks_by_type_id
+            .remove(&TypeId::of::<R>())
+            .map(unwrap_resource_lock)
+    }
+    pub(crate) fn contains<R: 'static + Send + Sync>(&self) -> bool {
+        self.resource_locks_by_type_id
+            .contains_key(&TypeId::of::<R>())
+    }
+    pub(crate) fn get<R: 'static + Send + Sync>(
+        &self,
+    ) -> Result<Option<ResourceRef<'_, R>>, TitanError> {
+        let resource_lock = match self.get_resource_lock::<R>() {
+            Some(resource_lock) => resource_lock,
+            None => return Ok(None),
+        };
+        let resource = resource_lock
+            .try_read()
+            .map_err(map_try_lock_error::<R, _>)?;
+        Ok(Some(ResourceRef { resource }))
+    }
+    pub(crate) fn get_mut<R: 'static + Send + Sync>(
+        &self,
+    ) -> Result<Option<ResourceRefMut<'_, R>>, TitanError> {
+        let resource_lock = match self.get_resource_lock::<R>() {
+            Some(resource_lock) => resource_lock,
+            None => return Ok(None),
+        };
+        let resource = resource_lock
+            .try_write()
+            .map_err(map_try_lock_error::<R, _>)?;
+        Ok(Some(ResourceRefMut { resource }))
+    }
+    ///
+    /// Move all resources of `other` into these resources, replacing resources of the same type.
+    ///
+    pub(crate) fn append(&mut self, other: Resources) {
+        self.resource_locks_by_type_id
+            .extend(other.resource_locks_by_type_id);
+    }
+    ///
+    /// Move the resources of `other` whose type is not present into these resources, keeping
+    /// existing resources.
+    ///
+    pub(crate) fn append_missing(&mut self, other: Resources) {
+        for (type_id, resource_lock) in other.resource_locks_by_type_id {
+            self.resource_locks_by_type_id
+                .entry(type_id)
+                .or_insert(resource_lock);
+        }
+    }
+    fn get_resource_lock<R: 'static + Send + Sync>(&self) -> Option<&RwLock<R>> {
+        self.resource_locks_by_type_id
+            .get(&TypeId::of::<R>())
+            .map(|resource_lock| {
+                resource_lock
+                    .downcast_ref::<RwLock<R>>()
+                    .expect("Could not downcast to lock of resource")
+            })
+    }
+}
+
+fn unwrap_resource_lock<R: 'static>(resource_lock: Box<dyn Any + Send + Sync>) -> R {
+    resource_lock
+        .downcast::<RwLock<R>>()
+        .expect("Could not downcast to lock of resource")
+        .into_inner()
+        .unwrap_or_else(|error| error.into_inner())
+}
+
+fn map_try_lock_error<R, G>(error: TryLockError<G>) -> TitanError {
+    match error {
+        TryLockError::WouldBlock => TitanError::ResourceLockConflict(type_name::<R>().to_string()),
+        TryLockError::Poisoned(_) => TitanError::ResourceLockPoisoned(type_name::<R>().to_string()),
+    }
+}
+
+///
+/// Read access to a resource. Holds the read lock of the resource.
+///
+pub struct ResourceRef<'a, R> {
+    resource: RwLockReadGuard<'a, R>,
+}
+impl<'a, R> Deref for ResourceRef<'a, R> {
+    type Target = R;
+    fn deref(&self) -> &R {
+        &self.resource
+    }
+}
+
+///
+/// Write access to a resource. Holds the write lock of the resource.
+///
+pub struct ResourceRefMut<'a, R> {
+    resource: RwLockWriteGuard<'a, R>,
+}
+impl<'a, R> Deref for ResourceRefMut<'a, R> {
+    type Target = R;
+    fn deref(&self) -> &R {
+        &self.resource
+    }
+}
+impl<'a, R> DerefMut for ResourceRefMut<'a, R> {
+    fn deref_mut(&mut self) -> &mut R {
+        &mut self.resource
+    }
+}
@@ -0,0 +1,382 @@
+use crate::system::{Access, IntoSystem, System};
+use crate::{TitanError, ECS};
+use std::{any::Any, collections::HashSet, panic, sync::Mutex, thread};
+
+///
+/// System together with its label and ordering constraints, created from a system with
+/// `label`, `before` or `after`.
+///
+pub struct SystemDescriptor {
+    system: Box<dyn System>,
+    label: Option<String>,
+    before: Vec<String>,
+    after: Vec<String>,
+}
+
+///
+/// Conversion into a `SystemDescriptor`, implemented for every `IntoSystem`.
+///
+pub trait IntoSystemDescriptor<Params> {
+    fn into_descriptor(self) -> SystemDescriptor;
+    ///
+    /// Label the system, so other systems of the stage can be ordered relative to it. Several
+    /// systems may share a label.
+    ///
+    fn label(self, label: &str) -> SystemDescriptor
+    where
+        Self: Sized,
+    {
+        let mut descriptor = self.into_descriptor();
+        descriptor.label = Some(label.to_string());
+        descriptor
+    }
+    ///
+    /// Run the system before the systems of the stage with the given label.
+    ///
+    fn before(self, label: &str) -> SystemDescriptor
+    where
+        Self: Sized,
+    {
+        let mut descriptor = self.into_descriptor();
+        descriptor.before.push(label.to_string());
+        descriptor
+    }
+    ///
+    /// Run the system after the systems of the stage with the given label.
+    ///
+    fn after(self, label: &str) -> SystemDescriptor
+    where
+        Self: Sized,
+    {
+        let mut descriptor = self.into_descriptor();
+        descriptor.after.push(label.to_string());
+        descriptor
+    }
+}
+impl IntoSystemDescriptor<()> for SystemDescriptor {
+    fn into_descriptor(self) -> SystemDescriptor {
+        self
+    }
+}
+impl<S: IntoSystem<Params>, Params> IntoSystemDescriptor<(S::System, Params)> for S {
+    fn into_descriptor(self) -> SystemDescriptor {
+        SystemDescriptor {
+            system: Box::new(self.into_system()),
+            label: None,
+            before: Vec::new(),
+            after: Vec::new(),
+        }
+    }
+}
+
+///
+/// System of a stage, with the tick at which it last ran.
+///
+struct ScheduledSystem {
+    descriptor: SystemDescriptor,
+    last_run_tick: u64,
+}
+
+///
+/// How a `Schedule` runs the systems of a stage.
+///
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ExecutionMode {
+    ///
+    /// Run systems one after the other on the calling thread.
+    ///
+    #[default]
+    Sequential,
+    ///
+    /// Run systems whose `Access`es are compatible at the same time, on up to the given number of
+    /// threads. Systems whose accesses conflict still run in the order of the stage.
+    ///
+    Parallel(usize),
+}
+
+struct Stage {
+    name: String,
+    systems: Vec<ScheduledSystem>,
+    ///
+    /// Indices of systems grouped into batches which run one after the other, or `None` if
+    /// systems have been added since the batches were last computed.
+    ///
+    batches: Option<Vec<Vec<usize>>>,
+}
+impl Stage {
+    ///
+    /// Group systems into batches of systems which may run at the same time. With
+    /// `ExecutionMode::Sequential` each batch holds a single system.
+    ///
+    fn compute_batches(
+        &self,
+        execution_mode: ExecutionMode,
+    ) -> Result<Vec<Vec<usize>>, TitanError> {
+        let (order, dependencies) = self.compute_order()?;
+        if execution_mode == ExecutionMode::Sequential {
+            return Ok(order.into_iter().map(|i| vec![i]).collect());
+        }
+
+        // Put each system in the batch after the last batch holding a system it must run after
+        // or a system ordered before it whose access conflicts with its own
+        let accesses: Vec<Access> = self
+            .systems
+            .iter()
+            .map(|scheduled_system| scheduled_system.descriptor.system.access())
+            .collect();
+        let mut batches: Vec<Vec<usize>> = Vec::new();
+        let mut batch_by_system = vec![0; self.systems.len()];
+        for (position, i) in order.iter().enumerate() {
+            let batch = order[..position]
+                .iter()
+                .filter(|j| {
+                    dependencies[*i].contains(*j) || !accesses[*i].is_compatible(&accesses[**j])
+                })
+                .map(|j| batch_by_system[*j] + 1)
+                .max()
+                .unwrap_or(0);
+            if batch == batches.len() {
+                batches.push(Vec::new());
+            }
+            batches[batch].push(*i);
+            batch_by_system[*i] = batch;
+        }
+        Ok(batches)
+    }
+    ///
+    /// Order systems so each runs after the systems it must run after, keeping the order in which
+    /// systems were added otherwise. Constraints on labels no system of the stage has are
+    /// ignored.
+    ///
+    /// Returns the order together with the systems each system must run after.
+    ///
+    fn compute_order(&self) -> Result<(Vec<usize>, Vec<HashSet<usize>>), TitanError> {
+        let system_count = self.systems.len();
+        let has_label = |index: usize, label: &String| {
+            self.systems[index].descriptor.label.as_ref() == Some(label)
+        };
+
+        // Collect the systems each system must run after
+        let mut dependencies = vec![HashSet::new(); system_count];
+        for (i, scheduled_system) in self.systems.iter().enumerate() {
+            for label in scheduled_system.descriptor.after.iter() {
+                for j in (0..system_count).filter(|j| *j != i && has_label(*j, label)) {
+                    dependencies[i].insert(j);
+                }
+            }
+            for label in scheduled_system.descriptor.before.iter() {
+                for j in (0..system_count).filter(|j| *j != i && has_label(*j, label)) {
+                    dependencies[j].insert(i);
+                }
+            }
+        }
+
+        // Repeatedly take the first system whose dependencies have all been ordered
+        let mut order = Vec::with_capacity(system_count);
+        let mut is_ordered = vec![false; system_count];
+        while order.len() < system_count {
+            let next = (0..system_count)
+                .find(|i| !is_ordered[*i] && dependencies[*i].iter().all(|j| is_ordered[*j]));
+            match next {
+                Some(i) => {
+                    is_ordered[i] = true;
+                    order.push(i);
+                }
+                None => {
+                    let i = (0..system_count)
+                        .find(|i| !is_ordered[*i])
+                        .expect("Could not find unordered system");
+                    let label = self.systems[i].descriptor.label.clone();
+                    return Err(TitanError::SystemOrderCycle(
+                        label.unwrap_or_else(|| self.name.clone()),
+                    ));
+                }
+            }
+        }
+        Ok((order, dependencies))
+    }
+    ///
+    /// Run the systems of a batch, on up to `thread_count` scoped threads if the batch holds
+    /// several systems. Returns the first error of a system once every thread has finished, or
+    /// resumes the panic of a system which panicked.
+    ///
+    fn run_batch(
+        &mut self,
+        batch: &[usize],
+        ecs: &ECS,
+        thread_count: Option<usize>,
+    ) -> Result<(), TitanError> {
+        let thread_count = match (batch, thread_count) {
+            ([_, _, ..], Some(thread_count)) => thread_count.clamp(1, batch.len()),
+            _ => {
+                return batch.iter().try_for_each(|i| {
+                    let scheduled_system = &mut self.systems[*i];
+                    scheduled_system
+                        .descriptor
+                        .system
+                        .run(ecs, scheduled_system.last_run_tick)
+                })
+            }
+        };
+
+        // Each thread takes the next system of the batch until none are left
+        let scheduled_systems = Mutex::new(
+            self.systems
+                .iter_mut()
+                .enumerate()
+                .filter(|(i, _)| batch.contains(i))
+                .map(|(_, scheduled_system)| scheduled_system),
+        );
+        thread::scope(|scope| {
+            let workers: Vec<_> = (0..thread_count)
+                .map(|_| {
+                    scope.spawn(|| loop {
+                        let scheduled_system = scheduled_systems
+                            .lock()
+                            .expect("Could not lock systems of batch")
+                            .next();
+                        match scheduled_system {
+                            Some(scheduled_system) => scheduled_system
+                                .descriptor
+                                .system
+                                .run(ecs, scheduled_system.last_run_tick)?,
+                            None => return Ok(()),
+                        }
+                    })
+                })
+                .collect();
+
+            // Join every thread, even after one failed, before resuming a panic
+            let mut error = None;
+            let mut panic_payload: Option<Box<dyn Any + Send>> = None;
+            for worker in workers {
+                match worker.join() {
+                    Ok(Ok(())) => {}
+                    Ok(Err(worker_error)) => {
+                        error.get_or_insert(worker_error);
+                    }
+                    Err(payload) => {
+                        panic_payload.get_or_insert(payload);
+                    }
+                }
+            }
+            if let Some(payload) = panic_payload {
+                panic::resume_unwind(payload);
+            }
+            error.map_or(Ok(()), Err)
+        })
+    }
+}
+
+///
+/// Ordered stages of systems, run against an ECS one stage after the other.
+///
+#[derive(Default)]
+pub struct Schedule {
+    stages: Vec<Stage>,
+    execution_mode: ExecutionMode,
+}
+impl Schedule {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    ///
+    /// Add a stage which runs after all stages added before it. Adding a stage which already
+    /// exists does nothing.
+    ///
+    pub fn add_stage(&mut self, name: &str) -> &mut Self {
+        if !self.stages.iter().any(|stage| stage.name == name) {
+            self.stages.push(Stage {
+                name: name.to_string(),
+                systems: Vec::new(),
+                batches: None,
+            });
+        }
+        self
+    }
+    ///
+    /// Set how systems of a stage are run. Defaults to running them sequentially.
+    ///
+    pub fn set_execution_mode(&mut self, execution_mode: ExecutionMode) -> &mut Self {
+        self.execution_mode = execution_mode;
+        for stage in self.stages.iter_mut() {
+            stage.batches = None;
+        }
+        self
+    }
+    ///
+    /// Add a system to the given stage. Systems of a stage run in the order they were added in,
+    /// unless ordered otherwise with `before` and `after`.
+    ///
+    pub fn add_system<Params>(
+        &mut self,
+        stage: &str,
+        system: impl IntoSystemDescriptor<Params>,
+    ) -> &mut Self {
+        self.try_add_system(stage, system)
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+    ///
+    /// Add a system to the given stage.
+    /// Returns an error if the stage has not been added.
+    ///
+    pub fn try_add_system<Params>(
+        &mut self,
+        stage: &str,
+        system: impl IntoSystemDescriptor<Params>,
+    ) -> Result<&mut Self, TitanError> {
+        let stage = self
+            .stages
+            .iter_mut()
+            .find(|existing_stage| existing_stage.name == stage)
+            .ok_or_else(|| TitanError::UnknownStage(stage.to_string()))?;
+        stage.systems.push(ScheduledSystem {
+            descriptor: system.into_descriptor(),
+            last_run_tick: 0,
+        });
+        stage.batches = None;
+        Ok(self)
+    }
+    ///
+    /// Run every system, stage by stage. Each run of the schedule is one tick of the ECS, see
+    /// `ECS::increment_change_tick`.
+    ///
+    pub fn run(&mut self, ecs: &mut ECS) {
+        self.try_run(ecs)
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+    ///
+    /// Run every system, stage by stage.
+    /// Returns an error, without running the remaining systems, if the systems of a stage can not
+    /// be ordered or a system fails to fetch its parameters. A system which panics on a thread of
+    /// the schedule resumes its panic on the calling thread.
+    ///
+    pub fn try_run(&mut self, ecs: &mut ECS) -> Result<(), TitanError> {
+        let thread_count = match self.execution_mode {
+            ExecutionMode::Sequential => None,
+            ExecutionMode::Parallel(thread_count) => Some(thread_count),
+        };
+        for stage in self.stages.iter_mut() {
+            let batches = match stage.batches.take() {
+                Some(batches) => batches,
+                None => stage.compute_batches(self.execution_mode)?,
+            };
+            let result = batches.iter().try_for_each(|batch| {
+                let change_tick = ecs.get_change_tick();
+                stage.run_batch(batch, ecs, thread_count)?;
+
+                // Later batches see the changes of this batch, while its systems do not see their
+                // own changes on their next run
+                for i in batch.iter() {
+                    stage.systems[*i].last_run_tick = change_tick;
+                }
+                ecs.advance_change_tick();
+                Ok(())
+            });
+            stage.batches = Some(batches);
+            result?;
+        }
+        ecs.increment_change_tick();
+        Ok(())
+    }
+}
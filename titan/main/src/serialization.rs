@@ -1,49 +1,939 @@
-use crate::{bundle::BundleKind, registry::Registry, storage::Storage};
-use serde_json::Value;
+use crate::{
+    bundle::BundleKind,
+    entity::{Entity, EntitySlots},
+    query::ArchetypeFilter,
+    registry::{ArchetypeEntityDeserializeFn, Registry},
+    resource::Resources,
+    storage::{Archetype, ComponentVec, Storage},
+    ComponentKind, ComponentMeta, TitanError,
+};
+use serde::{
+    de::{DeserializeSeed, Error, IgnoredAny, MapAccess, SeqAccess, Visitor},
+    Deserialize, Deserializer, Serialize,
+};
+use serde_json::{Map, Value};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt::Formatter,
+    io::{Read, Write},
+};
+
+///
+/// Encoding used to save and load the storage. See `Json` and `Binary`.
+///
+pub trait Format {
+    ///
+    /// Borrowed form of the serialized data, such as `str` or `[u8]`.
+    ///
+    type Serial: ?Sized + ToOwned;
+    fn serialize(
+        storage: &Storage,
+        registry: &Registry,
+        options: &SerializeOptions,
+    ) -> Result<<Self::Serial as ToOwned>::Owned, TitanError>;
+    fn deserialize(serial: &Self::Serial, registry: &Registry) -> Result<Storage, TitanError>;
+}
+
+///
+/// Selection of the entities and components written by a save. Selects every component of every
+/// entity by default.
+///
+#[derive(Default)]
+pub struct SerializeOptions {
+    archetype_filter: Option<fn(&Archetype) -> bool>,
+    component_kinds: Option<HashSet<ComponentKind>>,
+    canonical: bool,
+    resources: bool,
+}
+impl SerializeOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    ///
+    /// Write archetypes sorted by bundle kind and entities sorted by id, so identical worlds
+    /// produce identical saves.
+    ///
+    pub fn canonical(mut self) -> Self {
+        self.canonical = true;
+        self
+    }
+    ///
+    /// Only save entities in archetypes matching the filter `Q`, a tuple of `With` and `Without`
+    /// such as `(With<Persistent>,)`. `Added` and `Changed` depend on when a query last ran, so
+    /// they can not select what is saved.
+    ///
+    pub fn with_query<Q: ArchetypeFilter>(mut self) -> Self {
+        self.archetype_filter = Some(Q::matches_archetype);
+        self
+    }
+    ///
+    /// Only save components of the given kinds. Entities which have none of these components are
+    /// not saved.
+    ///
+    pub fn with_component_kinds(mut self, component_kinds: &[ComponentKind]) -> Self {
+        self.component_kinds
+            .get_or_insert_with(HashSet::new)
+            .extend(component_kinds.iter().cloned());
+        self
+    }
+    ///
+    /// Only save components of kind `T` and of other kinds selected by this method or
+    /// `with_component_kinds`.
+    ///
+    pub fn with_component<T: ComponentMeta>(self) -> Self {
+        self.with_component_kinds(&[T::get_component_kind()])
+    }
+    ///
+    /// Save registered resources even though only some entities or components are saved.
+    /// Unfiltered saves always include resources.
+    ///
+    pub fn with_resources(mut self) -> Self {
+        self.resources = true;
+        self
+    }
+    fn includes_resources(&self) -> bool {
+        self.resources || (self.archetype_filter.is_none() && self.component_kinds.is_none())
+    }
+    ///
+    /// Serialize the resources to save with the given function, or none if the save is filtered
+    /// without `with_resources`.
+    ///
+    fn select_resources<C>(
+        &self,
+        storage: &Storage,
+        serialize_resources: impl FnOnce(&Resources) -> Result<BTreeMap<String, C>, TitanError>,
+    ) -> Result<BTreeMap<String, C>, TitanError> {
+        if !self.includes_resources() {
+            return Ok(BTreeMap::new());
+        }
+        (serialize_resources)(&storage.resources)
+    }
+    fn includes_component_kind(&self, component_kind: &ComponentKind) -> bool {
+        self.component_kinds
+            .as_ref()
+            .map(|component_kinds| component_kinds.contains(component_kind))
+            .unwrap_or(true)
+    }
+    ///
+    /// Get the archetypes to save, sorted by bundle kind if the output is canonical.
+    ///
+    fn order_archetypes<'a>(&self, storage: &'a Storage) -> Vec<(&'a BundleKind, &'a Archetype)> {
+        let mut archetypes: Vec<_> = storage.archetype_by_bundle_kind.iter().collect();
+        if self.canonical {
+            archetypes.sort_by(|(a, _), (b, _)| a.0.cmp(&b.0));
+        }
+        archetypes
+    }
+    ///
+    /// Get the rows of the archetype sorted by entity id if the output is canonical, or `None` if
+    /// rows are saved in storage order.
+    ///
+    fn order_rows(&self, archetype: &Archetype) -> Option<Vec<usize>> {
+        if !self.canonical {
+            return None;
+        }
+        let entity_ids = archetype.get_entity_ids();
+        let mut rows: Vec<usize> = (0..entity_ids.len()).collect();
+        rows.sort_by_key(|row| (entity_ids[*row].index(), entity_ids[*row].generation()));
+        Some(rows)
+    }
+    ///
+    /// Get the state of the entity allocator to save, recording entities which are not saved as
+    /// despawned so their handles stay stale after loading.
+    ///
+    fn select_entity_slots(&self, storage: &Storage, registry: &Registry) -> EntitySlots {
+        let mut entity_slots = storage.get_entity_slots();
+        for (_, archetype) in self.order_archetypes(storage) {
+            if self.select_component_kinds(archetype, registry).is_some() {
+                continue;
+            }
+            let rows = self
+                .order_rows(archetype)
+                .unwrap_or_else(|| (0..archetype.get_entity_count()).collect());
+            for row in rows {
+                entity_slots.free(archetype.get_entity_id_at_index_unchecked(row));
+            }
+        }
+        entity_slots
+    }
+    ///
+    /// Get the kinds of the components to save of the entities in the archetype, or `None` if
+    /// none of its entities are saved. Transient components are never saved.
+    ///
+    fn select_component_kinds(
+        &self,
+        archetype: &Archetype,
+        registry: &Registry,
+    ) -> Option<Vec<ComponentKind>> {
+        if let Some(archetype_filter) = self.archetype_filter {
+            if !(archetype_filter)(archetype) {
+                return None;
+            }
+        }
+        let all_component_kinds = archetype.get_component_kinds();
+        let has_components = !all_component_kinds.is_empty();
+        let component_kinds: Vec<_> = all_component_kinds
+            .into_iter()
+            .filter(|component_kind| {
+                self.includes_component_kind(component_kind)
+                    && !registry.is_transient_component_kind(component_kind)
+            })
+            .collect();
+        if component_kinds.is_empty() && (has_components || self.component_kinds.is_some()) {
+            return None;
+        }
+        Some(component_kinds)
+    }
+}
+
+///
+/// Human readable format, saving each entity as a JSON object keyed by component kind.
+///
+pub struct Json;
+impl Format for Json {
+    type Serial = str;
+    fn serialize(
+        storage: &Storage,
+        registry: &Registry,
+        options: &SerializeOptions,
+    ) -> Result<String, TitanError> {
+        Serializable::serialize(storage, registry, options)
+    }
+    fn deserialize(serial: &str, registry: &Registry) -> Result<Storage, TitanError> {
+        <Storage as Serializable<Storage>>::deserialize(serial, registry)
+    }
+}
+
+///
+/// Human readable format, saving one record per archetype holding its entities and one JSON array
+/// per component column.
+///
+pub struct ColumnarJson;
+impl Format for ColumnarJson {
+    type Serial = str;
+    fn serialize(
+        storage: &Storage,
+        registry: &Registry,
+        options: &SerializeOptions,
+    ) -> Result<String, TitanError> {
+        let save = serialize_archetype_records(
+            storage,
+            registry,
+            options,
+            |component_kind, archetype, rows| {
+                registry.serialize_component_vec_json(component_kind, archetype, rows)
+            },
+            options.select_resources(storage, |resources| {
+                registry.serialize_resources_json(resources)
+            })?,
+        )?;
+        serde_json::to_string(&save)
+            .map_err(|error| TitanError::SerializationFailed(error.to_string()))
+    }
+    fn deserialize(serial: &str, registry: &Registry) -> Result<Storage, TitanError> {
+        let mut save = serde_json::from_str::<ArchetypeSave<Value>>(serial)
+            .map_err(|error| TitanError::MalformedSaveData(error.to_string()))?;
+        let save_version = save.version;
+        let resource_values = std::mem::take(&mut save.resources);
+        let mut storage =
+            deserialize_archetype_records(save, registry, |component_kind, column| {
+                let column = match column {
+                    Value::Array(component_values) => Value::Array(
+                        component_values
+                            .into_iter()
+                            .map(|component_value| {
+                                registry.migrate_component(
+                                    component_kind,
+                                    component_value,
+                                    save_version,
+                                )
+                            })
+                            .collect(),
+                    ),
+                    column => column,
+                };
+                registry.deserialize_component_vec_json(component_kind, &column)
+            })?;
+        registry.deserialize_resources_json(resource_values, &mut storage.resources)?;
+        Ok(storage)
+    }
+}
+
+///
+/// Compact format, saving each archetype column as a single bincode encoded vec.
+///
+/// Columns are not self describing, so `Binary` saves are not migratable. Loading a `Binary`
+/// save which requires a migration returns an error.
+///
+pub struct Binary;
+impl Format for Binary {
+    type Serial = [u8];
+    fn serialize(
+        storage: &Storage,
+        registry: &Registry,
+        options: &SerializeOptions,
+    ) -> Result<Vec<u8>, TitanError> {
+        let save = serialize_archetype_records(
+            storage,
+            registry,
+            options,
+            |component_kind, archetype, rows| {
+                registry.serialize_component_vec_binary(component_kind, archetype, rows)
+            },
+            options.select_resources(storage, |resources| {
+                registry.serialize_resources_binary(resources)
+            })?,
+        )?;
+
+        // Resources and entity slots are written after the archetypes, so saves which predate
+        // them still load
+        let mut serial = bincode::serialize(&BinaryArchetypeSave {
+            version: save.version,
+            archetypes: save.archetypes,
+        })
+        .map_err(|error| TitanError::SerializationFailed(error.to_string()))?;
+        bincode::serialize_into(&mut serial, &save.resources)
+            .map_err(|error| TitanError::SerializationFailed(error.to_string()))?;
+        bincode::serialize_into(&mut serial, &save.entity_slots)
+            .map_err(|error| TitanError::SerializationFailed(error.to_string()))?;
+        Ok(serial)
+    }
+    fn deserialize(mut serial: &[u8], registry: &Registry) -> Result<Storage, TitanError> {
+        let binary_save = bincode::deserialize_from::<_, BinaryArchetypeSave>(&mut serial)
+            .map_err(|error| TitanError::MalformedSaveData(error.to_string()))?;
+        let resource_bytes = if serial.is_empty() {
+            BTreeMap::new()
+        } else {
+            bincode::deserialize_from::<_, BTreeMap<String, Vec<u8>>>(&mut serial)
+                .map_err(|error| TitanError::MalformedSaveData(error.to_string()))?
+        };
+        let entity_slots = if serial.is_empty() {
+            None
+        } else {
+            bincode::deserialize::<Option<EntitySlots>>(serial)
+                .map_err(|error| TitanError::MalformedSaveData(error.to_string()))?
+        };
+        let save = ArchetypeSave {
+            version: binary_save.version,
+            archetypes: binary_save.archetypes,
+            resources: BTreeMap::new(),
+            entity_slots,
+        };
+        let save_version = save.version;
+        let mut storage =
+            deserialize_archetype_records(save, registry, |component_kind, column| {
+                // Binary columns can not be decoded without the type they were saved from
+                if registry.has_pending_migrations(component_kind, save_version) {
+                    return Err(TitanError::MalformedSaveData(format!(
+                        "Migrations of component_kind {:?} can not be applied to binary save data",
+                        component_kind.0
+                    )));
+                }
+                registry.deserialize_component_vec_binary(component_kind, &column)
+            })?;
+        registry.deserialize_resources_binary(resource_bytes, &mut storage.resources)?;
+        Ok(storage)
+    }
+}
+
+///
+/// Save holding one record per archetype, tagged with the save version it was written at.
+///
+#[derive(Serialize, Deserialize)]
+struct ArchetypeSave<C> {
+    version: u32,
+    archetypes: Vec<ArchetypeRecord<C>>,
+    #[serde(default)]
+    resources: BTreeMap<String, C>,
+    #[serde(default)]
+    entity_slots: Option<EntitySlots>,
+}
+
+///
+/// Archetypes of a binary save, followed by the encoded resources of the save if any.
+///
+#[derive(Serialize, Deserialize)]
+struct BinaryArchetypeSave {
+    version: u32,
+    archetypes: Vec<ArchetypeRecord<Vec<u8>>>,
+}
+
+///
+/// Saved archetype, holding each component column encoded as a whole.
+///
+#[derive(Serialize, Deserialize)]
+struct ArchetypeRecord<C> {
+    bundle_kind: String,
+    entity_ids: Vec<Entity>,
+    columns: BTreeMap<String, C>,
+}
+
+fn serialize_archetype_records<C>(
+    storage: &Storage,
+    registry: &Registry,
+    options: &SerializeOptions,
+    serialize_column: impl Fn(&ComponentKind, &Archetype, Option<&[usize]>) -> Result<C, TitanError>,
+    resources: BTreeMap<String, C>,
+) -> Result<ArchetypeSave<C>, TitanError> {
+    if storage.has_preserved_components() {
+        return Err(TitanError::SerializationFailed(
+            "Preserved unknown components can only be saved as Json".to_string(),
+        ));
+    }
+    let mut archetype_records = Vec::new();
+    for (_, archetype) in options.order_archetypes(storage) {
+        if archetype.get_entity_count() == 0 {
+            continue;
+        }
+        let component_kinds = match options.select_component_kinds(archetype, registry) {
+            Some(component_kinds) => component_kinds,
+            None => continue,
+        };
+        let rows = options.order_rows(archetype);
+        let entity_ids = match rows.as_ref() {
+            Some(rows) => rows
+                .iter()
+                .map(|row| archetype.get_entity_id_at_index_unchecked(*row))
+                .collect(),
+            None => archetype.get_entity_ids().to_vec(),
+        };
+
+        // Serialize each selected component vec of archetype as a whole
+        let mut columns = BTreeMap::new();
+        for component_kind in component_kinds.iter() {
+            let column = (serialize_column)(component_kind, archetype, rows.as_deref())?;
+            columns.insert(component_kind.0.clone(), column);
+        }
+        archetype_records.push(ArchetypeRecord {
+            bundle_kind: BundleKind::from_component_kinds(&component_kinds).0,
+            entity_ids,
+            columns,
+        });
+    }
+    Ok(ArchetypeSave {
+        version: registry.get_save_version(),
+        archetypes: archetype_records,
+        resources,
+        entity_slots: Some(options.select_entity_slots(storage, registry)),
+    })
+}
+
+fn deserialize_archetype_records<C>(
+    save: ArchetypeSave<C>,
+    registry: &Registry,
+    deserialize_column: impl Fn(&ComponentKind, C) -> Result<Box<dyn ComponentVec>, TitanError>,
+) -> Result<Storage, TitanError> {
+    registry.check_save_version(save.version)?;
+    let mut storage = Storage::new();
+    if let Some(entity_slots) = save.entity_slots {
+        storage.restore_entity_slots(entity_slots)?;
+    }
+    let loaded_entity_count = save
+        .archetypes
+        .iter()
+        .map(|archetype_record| archetype_record.entity_ids.len())
+        .sum();
+    for archetype_record in save.archetypes {
+        let bundle_kind = BundleKind(archetype_record.bundle_kind);
+        let component_kinds: Vec<_> = archetype_record
+            .columns
+            .keys()
+            .map(|component_kind| ComponentKind(component_kind.clone()))
+            .collect();
+        if BundleKind::from_component_kinds(&component_kinds) != bundle_kind {
+            return Err(TitanError::MalformedSaveData(format!(
+                "Columns do not match bundle_kind {:?}",
+                bundle_kind.0
+            )));
+        }
+
+        // Drop columns of unknown component kinds if tolerated
+        let mut bundle_kind = bundle_kind;
+        let mut columns = archetype_record.columns;
+        let unknown_component_kinds: Vec<_> = component_kinds
+            .iter()
+            .filter(|component_kind| !registry.has_component_kind(component_kind))
+            .cloned()
+            .collect();
+        if let Some(unknown_component_kind) = unknown_component_kinds.first() {
+            match registry.get_unknown_component_policy() {
+                UnknownComponentPolicy::Error => {}
+                UnknownComponentPolicy::Drop => {
+                    for unknown_component_kind in unknown_component_kinds.iter() {
+                        columns.remove(&unknown_component_kind.0);
+                    }
+                    let known_component_kinds: Vec<_> = columns
+                        .keys()
+                        .map(|component_kind| ComponentKind(component_kind.clone()))
+                        .collect();
+                    bundle_kind = BundleKind::from_component_kinds(&known_component_kinds);
+                }
+                UnknownComponentPolicy::Preserve => {
+                    return Err(TitanError::UnknownComponentKind(
+                        unknown_component_kind.clone(),
+                    ));
+                }
+            }
+        }
+
+        // Deserialize each component vec of archetype as a whole
+        let mut component_vec_locks_by_component_kind = HashMap::new();
+        for (component_kind, column) in columns.into_iter() {
+            let component_kind = ComponentKind(component_kind);
+            let component_vec_lock = (deserialize_column)(&component_kind, column)?;
+            component_vec_locks_by_component_kind.insert(component_kind, component_vec_lock);
+        }
+        let archetype = Archetype::from_component_vecs(
+            archetype_record.entity_ids,
+            component_vec_locks_by_component_kind,
+        )?;
+        storage.insert_archetype(bundle_kind, archetype, loaded_entity_count)?;
+    }
+    Ok(storage)
+}
+
+///
+/// How loading handles components of kinds which have not been registered, such as components of
+/// a mod which is no longer installed.
+///
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum UnknownComponentPolicy {
+    ///
+    /// Fail the load.
+    ///
+    Error,
+    ///
+    /// Drop unknown components, placing each entity in the archetype of its remaining components.
+    ///
+    Drop,
+    ///
+    /// Drop unknown components like `Drop`, but keep their serialized values to be written back
+    /// on the next save. Only supported by the `Json` format.
+    ///
+    Preserve,
+}
 
 pub trait Serializable<T> {
-    fn serialize(&self, registry: &Registry) -> String;
-    fn deserialize(serial: &str, registry: &Registry) -> T;
+    fn serialize(
+        &self,
+        registry: &Registry,
+        options: &SerializeOptions,
+    ) -> Result<String, TitanError>;
+    fn deserialize(serial: &str, registry: &Registry) -> Result<T, TitanError>;
+    fn serialize_to<W: Write>(
+        &self,
+        registry: &Registry,
+        options: &SerializeOptions,
+        writer: W,
+    ) -> Result<(), TitanError>;
+    fn deserialize_from<R: Read>(reader: R, registry: &Registry) -> Result<T, TitanError>;
 }
 
 impl Serializable<Storage> for Storage {
-    fn serialize(&self, registry: &Registry) -> String {
-        let mut entity_values = Vec::new();
-        for (bundle_kind, archetype) in self.archetype_by_bundle_kind.iter() {
-            let archetype_entity_serialize_fn =
-                registry.bundle_kind_to_archetype_entity_serialize_fn(bundle_kind.clone());
-
-            // Serialize each entity in archetype
-            for i in 0..archetype.get_entity_count() {
-                let entity_value = (archetype_entity_serialize_fn)(i, &archetype, bundle_kind);
-                entity_values.push(entity_value);
-            }
-        }
-        let entities_string = serde_json::to_string(&entity_values).unwrap();
-        entities_string
-    }
-    fn deserialize(serial: &str, registry: &Registry) -> Storage {
-        let entity_values = serde_json::from_str::<Value>(serial)
-            .expect("Could not deserialize entities from JSON");
-        let entity_values = entity_values
-            .as_array()
-            .expect("Could not parse JSON value as array");
-        let mut storage = Storage::new();
-        for entity_value in entity_values {
-            let entity_object = entity_value
-                .as_object()
-                .expect("Could not parse JSON value as object");
-            let bundle_kind_string = entity_object
-                .get(&"bundle_kind".to_string())
-                .expect("Could not get bundle_kind on JSON value")
-                .as_str()
-                .expect("Could not parse JSON bundle_kind as str")
-                .to_string();
-            let bundle_kind = BundleKind(bundle_kind_string);
-            let archetype_entity_deserialize_fn =
-                registry.bundle_kind_to_archetype_entity_deserialize_fn(bundle_kind);
-            (archetype_entity_deserialize_fn)(entity_value, &mut storage);
-        }
-        storage
+    fn serialize(
+        &self,
+        registry: &Registry,
+        options: &SerializeOptions,
+    ) -> Result<String, TitanError> {
+        let mut serial = Vec::new();
+        self.serialize_to(registry, options, &mut serial)?;
+        String::from_utf8(serial)
+            .map_err(|error| TitanError::SerializationFailed(error.to_string()))
+    }
+    fn deserialize(serial: &str, registry: &Registry) -> Result<Storage, TitanError> {
+        deserialize_entities(&mut serde_json::Deserializer::from_str(serial), registry)
+    }
+    fn serialize_to<W: Write>(
+        &self,
+        registry: &Registry,
+        options: &SerializeOptions,
+        writer: W,
+    ) -> Result<(), TitanError> {
+        let mut writer = writer;
+        write!(writer, "{{\"version\":{},", registry.get_save_version()).map_err(TitanError::Io)?;
+        writer
+            .write_all(b"\"entity_slots\":")
+            .map_err(TitanError::Io)?;
+        serde_json::to_writer(&mut writer, &options.select_entity_slots(self, registry))
+            .map_err(map_json_write_error)?;
+        writer.write_all(b",").map_err(TitanError::Io)?;
+        let resource_values = options.select_resources(self, |resources| {
+            registry.serialize_resources_json(resources)
+        })?;
+        if !resource_values.is_empty() {
+            writer
+                .write_all(b"\"resources\":")
+                .map_err(TitanError::Io)?;
+            serde_json::to_writer(&mut writer, &resource_values).map_err(map_json_write_error)?;
+            writer.write_all(b",").map_err(TitanError::Io)?;
+        }
+        writer
+            .write_all(b"\"entities\":[")
+            .map_err(TitanError::Io)?;
+        let mut is_first_entity = true;
+        for (bundle_kind, archetype) in options.order_archetypes(self) {
+            let component_kinds = match options.select_component_kinds(archetype, registry) {
+                Some(component_kinds) => component_kinds,
+                None => continue,
+            };
+
+            // Serialize with the function registered for the selected components, or build one
+            // if only some of the components of the archetype are selected
+            let selected_bundle_kind = BundleKind::from_component_kinds(&component_kinds);
+            let selected_archetype_entity_serialize_fn;
+            let archetype_entity_serialize_fn = match registry
+                .bundle_kind_to_archetype_entity_serialize_fn(selected_bundle_kind.clone())
+            {
+                Ok(archetype_entity_serialize_fn) => archetype_entity_serialize_fn,
+                Err(error) if selected_bundle_kind == *bundle_kind => return Err(error),
+                Err(_) => {
+                    selected_archetype_entity_serialize_fn =
+                        registry.build_archetype_entity_serialize_fn(&component_kinds)?;
+                    &selected_archetype_entity_serialize_fn
+                }
+            };
+
+            // Serialize and write each entity in archetype
+            let rows = options
+                .order_rows(archetype)
+                .unwrap_or_else(|| (0..archetype.get_entity_count()).collect());
+            for i in rows {
+                let mut entity_value =
+                    (archetype_entity_serialize_fn)(i, archetype, &selected_bundle_kind)?;
+                let entity = archetype.get_entity_id_at_index_unchecked(i);
+                if let Some(preserved_components) = self.get_preserved_components(entity) {
+                    write_back_preserved_components(
+                        &mut entity_value,
+                        preserved_components,
+                        options,
+                    );
+                }
+                if !is_first_entity {
+                    writer.write_all(b",").map_err(TitanError::Io)?;
+                }
+                is_first_entity = false;
+                serde_json::to_writer(&mut writer, &entity_value).map_err(map_json_write_error)?;
+            }
+        }
+        writer.write_all(b"]}").map_err(TitanError::Io)?;
+        writer.flush().map_err(TitanError::Io)
+    }
+    fn deserialize_from<R: Read>(reader: R, registry: &Registry) -> Result<Storage, TitanError> {
+        deserialize_entities(&mut serde_json::Deserializer::from_reader(reader), registry)
+    }
+}
+
+///
+/// Deserialize a JSON save of entities, restoring each entity as soon as it has been parsed
+/// rather than parsing the whole array up front.
+///
+/// Saves are objects holding the save `version`, the `entity_slots` of the entity allocator, the
+/// registered `resources` if any and the array of `entities`. A bare array of entities
+/// is loaded as a save of version 0 without entity slots or resources.
+///
+fn deserialize_entities<'de, R: serde_json::de::Read<'de>>(
+    deserializer: &mut serde_json::Deserializer<R>,
+    registry: &Registry,
+) -> Result<Storage, TitanError> {
+    let mut entity_deserializer = EntityDeserializer {
+        registry,
+        storage: Storage::new(),
+        unregistered_deserialize_fns: HashMap::new(),
+        save_version: None,
+        error: None,
+    };
+    let result = (&mut entity_deserializer)
+        .deserialize(&mut *deserializer)
+        .and_then(|_| deserializer.end());
+    if let Some(error) = entity_deserializer.error {
+        return Err(error);
+    }
+    result.map_err(map_json_read_error)?;
+    Ok(entity_deserializer.storage)
+}
+
+struct EntityDeserializer<'a> {
+    registry: &'a Registry,
+    storage: Storage,
+    unregistered_deserialize_fns: HashMap<BundleKind, ArchetypeEntityDeserializeFn>,
+    save_version: Option<u32>,
+    ///
+    /// Error raised while restoring an entity, which aborts deserialization.
+    ///
+    error: Option<TitanError>,
+}
+impl<'a> EntityDeserializer<'a> {
+    fn set_save_version(&mut self, save_version: u32) -> Result<(), TitanError> {
+        self.registry.check_save_version(save_version)?;
+        self.save_version = Some(save_version);
+        Ok(())
+    }
+    fn deserialize_entity(&mut self, entity_value: Value) -> Result<(), TitanError> {
+        let save_version = self
+            .save_version
+            .expect("Could not get save version of entity");
+        let mut entity_value = self.registry.migrate_entity(entity_value, save_version);
+        let unknown_components = match self.registry.get_unknown_component_policy() {
+            UnknownComponentPolicy::Error => Map::new(),
+            _ => take_unknown_components(&mut entity_value, self.registry)?,
+        };
+        let entity_object = entity_value
+            .as_object()
+            .ok_or_else(|| TitanError::malformed("Could not parse JSON value as object"))?;
+        let bundle_kind_string = entity_object
+            .get(&"bundle_kind".to_string())
+            .ok_or_else(|| TitanError::malformed("Could not get bundle_kind on JSON value"))?
+            .as_str()
+            .ok_or_else(|| TitanError::malformed("Could not parse JSON bundle_kind as str"))?
+            .to_string();
+        let bundle_kind = BundleKind(bundle_kind_string);
+        let archetype_entity_deserialize_fn = match self
+            .registry
+            .bundle_kind_to_archetype_entity_deserialize_fn(bundle_kind.clone())
+        {
+            Ok(archetype_entity_deserialize_fn) => archetype_entity_deserialize_fn,
+            Err(error) => {
+                if !self.unregistered_deserialize_fns.contains_key(&bundle_kind) {
+                    let archetype_entity_deserialize_fn = build_unregistered_deserialize_fn(
+                        &bundle_kind,
+                        entity_object,
+                        self.registry,
+                    )
+                    .ok_or(error)?;
+                    self.unregistered_deserialize_fns
+                        .insert(bundle_kind.clone(), archetype_entity_deserialize_fn);
+                }
+                &self.unregistered_deserialize_fns[&bundle_kind]
+            }
+        };
+        (archetype_entity_deserialize_fn)(&entity_value, &mut self.storage)?;
+
+        if self.registry.get_unknown_component_policy() == UnknownComponentPolicy::Preserve
+            && !unknown_components.is_empty()
+        {
+            let entity = Entity::deserialize(&entity_value["entity"])
+                .map_err(|error| TitanError::MalformedSaveData(error.to_string()))?;
+            self.storage
+                .set_preserved_components(entity, unknown_components);
+        }
+        Ok(())
+    }
+    ///
+    /// Keep the error to be returned once deserialization has been aborted with the returned
+    /// serde error.
+    ///
+    fn abort<E: Error>(&mut self, error: TitanError) -> E {
+        let message = error.to_string();
+        self.error = Some(error);
+        E::custom(message)
+    }
+}
+impl<'de, 'a> DeserializeSeed<'de> for &mut EntityDeserializer<'a> {
+    type Value = ();
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+impl<'de, 'a> Visitor<'de> for &mut EntityDeserializer<'a> {
+    type Value = ();
+    fn expecting(&self, formatter: &mut Formatter) -> std::fmt::Result {
+        formatter.write_str("a save object or an array of entities")
+    }
+    fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<(), A::Error> {
+        // A bare array of entities is loaded as a save of version 0
+        if let Err(error) = self.set_save_version(0) {
+            return Err(self.abort(error));
+        }
+        EntitiesSeed(self).visit_seq(seq)
+    }
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        let mut has_entities = false;
+        let mut buffered_entity_values = Vec::new();
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "version" => {
+                    let save_version = map.next_value::<u32>()?;
+                    if let Err(error) = self.set_save_version(save_version) {
+                        return Err(self.abort(error));
+                    }
+                }
+                "entity_slots" => {
+                    let entity_slots = map.next_value::<EntitySlots>()?;
+                    if let Err(error) = self.storage.restore_entity_slots(entity_slots) {
+                        return Err(self.abort(error));
+                    }
+                }
+                "resources" => {
+                    let resource_values = map.next_value::<Map<String, Value>>()?;
+                    if let Err(error) = self
+                        .registry
+                        .deserialize_resources_json(resource_values, &mut self.storage.resources)
+                    {
+                        return Err(self.abort(error));
+                    }
+                }
+                "entities" => {
+                    has_entities = true;
+                    if self.save_version.is_some() {
+                        map.next_value_seed(EntitiesSeed(&mut *self))?;
+                    } else {
+                        // Entities can only be migrated once the version is known
+                        buffered_entity_values = map.next_value::<Vec<Value>>()?;
+                    }
+                }
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        if self.save_version.is_none() {
+            return Err(A::Error::missing_field("version"));
+        }
+        if !has_entities {
+            return Err(A::Error::missing_field("entities"));
+        }
+        for entity_value in buffered_entity_values {
+            if let Err(error) = self.deserialize_entity(entity_value) {
+                return Err(self.abort(error));
+            }
+        }
+        Ok(())
+    }
+}
+
+///
+/// Restores each entity of an array as soon as it has been parsed.
+///
+struct EntitiesSeed<'b, 'a>(&'b mut EntityDeserializer<'a>);
+impl<'de, 'b, 'a> DeserializeSeed<'de> for EntitiesSeed<'b, 'a> {
+    type Value = ();
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+impl<'de, 'b, 'a> Visitor<'de> for EntitiesSeed<'b, 'a> {
+    type Value = ();
+    fn expecting(&self, formatter: &mut Formatter) -> std::fmt::Result {
+        formatter.write_str("an array of entities")
+    }
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        while let Some(entity_value) = seq.next_element::<Value>()? {
+            if let Err(error) = self.0.deserialize_entity(entity_value) {
+                return Err(self.0.abort(error));
+            }
+        }
+        Ok(())
+    }
+}
+
+///
+/// Remove the components of kinds which have not been registered from an entity object, updating
+/// its bundle kind to that of the remaining components.
+///
+/// Returns the removed components.
+///
+fn take_unknown_components(
+    entity_value: &mut Value,
+    registry: &Registry,
+) -> Result<Map<String, Value>, TitanError> {
+    let entity_object = entity_value
+        .as_object_mut()
+        .ok_or_else(|| TitanError::malformed("Could not parse JSON value as object"))?;
+    let unknown_keys: Vec<String> = entity_object
+        .keys()
+        .filter(|key| {
+            *key != "bundle_kind"
+                && *key != "entity"
+                && !registry.has_component_kind(&ComponentKind((*key).clone()))
+        })
+        .cloned()
+        .collect();
+    let mut unknown_components = Map::new();
+    if unknown_keys.is_empty() {
+        return Ok(unknown_components);
+    }
+    for key in unknown_keys {
+        let component_value = entity_object
+            .remove(&key)
+            .expect("Could not find unknown key");
+        unknown_components.insert(key, component_value);
+    }
+    let component_kinds: Vec<_> = entity_object
+        .keys()
+        .filter(|key| *key != "bundle_kind" && *key != "entity")
+        .map(|key| ComponentKind(key.clone()))
+        .collect();
+    entity_object.insert(
+        "bundle_kind".to_string(),
+        Value::from(BundleKind::from_component_kinds(&component_kinds).0),
+    );
+    Ok(unknown_components)
+}
+
+///
+/// Add preserved components back into a serialized entity object, updating its bundle kind to
+/// include them.
+///
+fn write_back_preserved_components(
+    entity_value: &mut Value,
+    preserved_components: &Map<String, Value>,
+    options: &SerializeOptions,
+) {
+    let entity_object = entity_value
+        .as_object_mut()
+        .expect("Could not get serialized entity as object");
+    for (key, component_value) in preserved_components.iter() {
+        if !entity_object.contains_key(key)
+            && options.includes_component_kind(&ComponentKind(key.clone()))
+        {
+            entity_object.insert(key.clone(), component_value.clone());
+        }
+    }
+    let component_kinds: Vec<_> = entity_object
+        .keys()
+        .filter(|key| *key != "bundle_kind" && *key != "entity")
+        .map(|key| ComponentKind(key.clone()))
+        .collect();
+    entity_object.insert(
+        "bundle_kind".to_string(),
+        Value::from(BundleKind::from_component_kinds(&component_kinds).0),
+    );
+}
+
+fn map_json_write_error(error: serde_json::Error) -> TitanError {
+    if error.is_io() {
+        TitanError::Io(error.into())
+    } else {
+        TitanError::SerializationFailed(error.to_string())
+    }
+}
+
+fn map_json_read_error(error: serde_json::Error) -> TitanError {
+    if error.is_io() {
+        TitanError::Io(error.into())
+    } else {
+        TitanError::MalformedSaveData(error.to_string())
+    }
+}
+
+///
+/// Build the deserialize function of an archetype which has not been registered from the component
+/// kinds of one of its entities, provided all of those components have been registered.
+///
+fn build_unregistered_deserialize_fn(
+    bundle_kind: &BundleKind,
+    entity_object: &Map<String, Value>,
+    registry: &Registry,
+) -> Option<ArchetypeEntityDeserializeFn> {
+    let component_kinds: Vec<_> = entity_object
+        .keys()
+        .filter(|key| *key != "bundle_kind" && *key != "entity")
+        .map(|key| ComponentKind(key.clone()))
+        .collect();
+    if BundleKind::from_component_kinds(&component_kinds) != *bundle_kind {
+        return None;
     }
+    registry
+        .build_archetype_entity_deserialize_fn(&component_kinds)
+        .ok()
 }
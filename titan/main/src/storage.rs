@@ -1,82 +1,783 @@
 use crate::{
     bundle::{Bundle, BundleKind},
-    query::Query,
-    ComponentKind, ComponentMeta, EntityId,
+    entity::{Entities, Entity, EntityCollisionPolicy, EntityLocation, EntitySlots},
+    query::{Filter, Query},
+    registry::Registry,
+    resource::Resources,
+    ComponentKind, ComponentMeta, TitanError,
+};
+use serde_json::{Map, Value};
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    RwLock, TryLockError,
 };
-use std::sync::RwLock;
 use std::{any::Any, sync::RwLockReadGuard};
-use std::{collections::HashMap, sync::RwLockWriteGuard};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::RwLockWriteGuard,
+};
 
 pub struct Storage {
-    current_entity_id: EntityId,
+    entities: Entities,
     pub(crate) archetype_by_bundle_kind: HashMap<BundleKind, Archetype>,
+    ///
+    /// Serialized components of unknown kinds, kept to be written back on the next save.
+    ///
+    preserved_components_by_entity: HashMap<Entity, Map<String, Value>>,
+    ///
+    /// Entities which had a component of each kind removed, including by despawning, together
+    /// with the tick of the removal.
+    ///
+    removals_by_component_kind: HashMap<ComponentKind, Vec<(Entity, u64)>>,
+    pub(crate) resources: Resources,
 }
 impl Storage {
     pub(crate) fn new() -> Self {
         Self {
-            current_entity_id: 0,
+            entities: Entities::new(),
             archetype_by_bundle_kind: HashMap::new(),
+            preserved_components_by_entity: HashMap::new(),
+            removals_by_component_kind: HashMap::new(),
+            resources: Resources::default(),
+        }
+    }
+    pub(crate) fn spawn<T: Bundle + 'static>(
+        &mut self,
+        bundle: T,
+        change_tick: u64,
+    ) -> Result<Entity, TitanError> {
+        let bundle_kind = T::get_bundle_kind();
+        self.ensure_archetype::<T>(&bundle_kind)?;
+        let entity = self.entities.allocate();
+        self.push_bundle(entity, bundle_kind, bundle, change_tick);
+        Ok(entity)
+    }
+    ///
+    /// Create the archetype for the bundle if it does not exist yet, validating the bundle.
+    ///
+    fn ensure_archetype<T: Bundle + 'static>(
+        &mut self,
+        bundle_kind: &BundleKind,
+    ) -> Result<(), TitanError> {
+        if let Some(archetype) = self.archetype_by_bundle_kind.get(bundle_kind) {
+            return T::check_component_types(archetype);
+        }
+        let mut component_kinds = T::get_component_kinds();
+        component_kinds.sort_by(|a, b| a.0.cmp(&b.0));
+        for pair in component_kinds.windows(2) {
+            if pair[0] == pair[1] {
+                return Err(TitanError::DuplicateComponentKind(pair[0].clone()));
+            }
+        }
+        self.archetype_by_bundle_kind
+            .insert(bundle_kind.clone(), Archetype::new());
+        Ok(())
+    }
+    ///
+    /// Spawn type erased components as the exact given entity, used when restoring entities from
+    /// serialized data. The entity has no ticks until `fill_ticks` is called.
+    ///
+    pub(crate) fn spawn_boxed_with_entity_id(
+        &mut self,
+        entity: Entity,
+        bundle_kind: BundleKind,
+        components: Vec<BoxedComponent>,
+    ) -> Result<(), TitanError> {
+        let index_bound = self
+            .entities
+            .get_restored_index_bound(self.entities.get_alive_count() + 1);
+        self.entities.reserve(entity, index_bound)?;
+        let archetype = self
+            .archetype_by_bundle_kind
+            .entry(bundle_kind.clone())
+            .or_insert_with(Archetype::new);
+
+        // Push components into archetype
+        archetype.push_entity_id(entity);
+        for component in components {
+            archetype.push_boxed_component(component);
         }
+
+        // Record location of entity
+        let row = archetype.get_entity_count() - 1;
+        self.entities
+            .set_location(entity, EntityLocation { bundle_kind, row });
+        Ok(())
     }
-    pub(crate) fn spawn<T: Bundle + 'static>(&mut self, bundle: T) {
-        self.spawn_with_entity_id(self.current_entity_id, bundle);
+    ///
+    /// Insert a whole archetype of restored entities, used when restoring entities from
+    /// serialized data. The entities have no ticks until `fill_ticks` is called.
+    ///
+    /// `loaded_entity_count` is the number of entities of the whole save, bounding the indices
+    /// restored entities may take if the save has no entity slots.
+    ///
+    pub(crate) fn insert_archetype(
+        &mut self,
+        bundle_kind: BundleKind,
+        archetype: Archetype,
+        loaded_entity_count: usize,
+    ) -> Result<(), TitanError> {
+        let index_bound = self.entities.get_restored_index_bound(loaded_entity_count);
+        for entity in archetype.get_entity_ids() {
+            self.entities.reserve(*entity, index_bound)?;
+        }
+
+        // Archetypes may be present more than once once unknown components have been dropped
+        let entity_ids = archetype.get_entity_ids().to_vec();
+        let target_archetype = self
+            .archetype_by_bundle_kind
+            .entry(bundle_kind.clone())
+            .or_insert_with(|| archetype.new_empty_with(&archetype.get_component_kinds()));
+        let first_row = target_archetype.get_entity_count();
+        target_archetype.append(archetype, &entity_ids);
+        for (i, entity) in entity_ids.into_iter().enumerate() {
+            self.entities.set_location(
+                entity,
+                EntityLocation {
+                    bundle_kind: bundle_kind.clone(),
+                    row: first_row + i,
+                },
+            );
+        }
+        Ok(())
+    }
+    ///
+    /// Move all entities of `other` into this storage, resolving entities whose index is already
+    /// in use according to `policy`.
+    ///
+    /// Returns the new handle of each moved entity by its handle in `other`. Entity references
+    /// held by moved components are remapped to the new handles, and moved components are
+    /// recorded as added at `change_tick`. Resources of `other` are only moved if this storage
+    /// has no resource of the same type.
+    ///
+    pub(crate) fn merge(
+        &mut self,
+        other: Storage,
+        policy: EntityCollisionPolicy,
+        registry: &Registry,
+        change_tick: u64,
+    ) -> Result<HashMap<Entity, Entity>, TitanError> {
+        let loaded_entities: Vec<Entity> = other
+            .archetype_by_bundle_kind
+            .values()
+            .flat_map(|archetype| archetype.get_entity_ids().iter().copied())
+            .collect();
+        let colliding_entities: HashSet<Entity> = loaded_entities
+            .iter()
+            .copied()
+            .filter(|entity| self.entities.get_alive_at(entity.index()).is_some())
+            .collect();
+        match policy {
+            EntityCollisionPolicy::Error => {
+                if let Some(entity) = colliding_entities.iter().next() {
+                    return Err(TitanError::EntityCollision(*entity));
+                }
+            }
+            EntityCollisionPolicy::Overwrite => {
+                for entity in colliding_entities.iter() {
+                    let living_entity = self
+                        .entities
+                        .get_alive_at(entity.index())
+                        .expect("Could not find living entity of colliding index");
+                    self.despawn(living_entity, change_tick);
+                }
+            }
+            EntityCollisionPolicy::Remap => {}
+        }
 
-        // TODO: Ensure entity_id is incremented correctly
-        // Increment entity_id for next spawn
-        self.current_entity_id = self.current_entity_id + 1;
+        // Restore indices of entities which do not collide before allocating fresh handles, so
+        // a fresh handle can not take the index of a loaded entity
+        self.entities.merge_slots(&other.entities);
+        let mut entity_map = HashMap::new();
+        for entity in loaded_entities.iter() {
+            if policy == EntityCollisionPolicy::Remap && colliding_entities.contains(entity) {
+                continue;
+            }
+            // Indices of loaded entities were bounded when loading and lie within merged slots
+            let restored_entity = self.entities.reserve(*entity, 0)?;
+            entity_map.insert(*entity, restored_entity);
+        }
+        for entity in colliding_entities.iter() {
+            if policy == EntityCollisionPolicy::Remap {
+                entity_map.insert(*entity, self.entities.allocate());
+            }
+        }
+
+        // Move preserved components of moved entities
+        for (entity, preserved_components) in other.preserved_components_by_entity.into_iter() {
+            self.preserved_components_by_entity
+                .insert(entity_map[&entity], preserved_components);
+        }
+
+        // Append archetypes and record locations of moved entities
+        for (bundle_kind, mut archetype) in other.archetype_by_bundle_kind.into_iter() {
+            registry.map_entities(&mut archetype, &entity_map);
+            let entity_ids: Vec<Entity> = archetype
+                .get_entity_ids()
+                .iter()
+                .map(|entity| entity_map[entity])
+                .collect();
+            let target_archetype = self
+                .archetype_by_bundle_kind
+                .entry(bundle_kind.clone())
+                .or_insert_with(|| archetype.new_empty_with(&archetype.get_component_kinds()));
+            let first_row = target_archetype.get_entity_count();
+            target_archetype.append(archetype, &entity_ids);
+            for (i, entity) in entity_ids.into_iter().enumerate() {
+                self.entities.set_location(
+                    entity,
+                    EntityLocation {
+                        bundle_kind: bundle_kind.clone(),
+                        row: first_row + i,
+                    },
+                );
+            }
+        }
+        self.fill_ticks(change_tick);
+        self.resources.append_missing(other.resources);
+        Ok(entity_map)
+    }
+    ///
+    /// Record every row which has no ticks yet, such as restored entities, as added and changed
+    /// at `change_tick`.
+    ///
+    pub(crate) fn fill_ticks(&mut self, change_tick: u64) {
+        for archetype in self.archetype_by_bundle_kind.values_mut() {
+            archetype.fill_ticks(change_tick);
+        }
+    }
+    ///
+    /// Get the component kinds of every archetype.
+    ///
+    pub(crate) fn get_archetype_component_kinds(&self) -> Vec<(BundleKind, Vec<ComponentKind>)> {
+        self.archetype_by_bundle_kind
+            .iter()
+            .map(|(bundle_kind, archetype)| (bundle_kind.clone(), archetype.get_component_kinds()))
+            .collect()
     }
-    pub(crate) fn spawn_with_entity_id<T: Bundle + 'static>(
+    fn push_bundle<T: Bundle + 'static>(
         &mut self,
-        entity_id: EntityId,
+        entity: Entity,
+        bundle_kind: BundleKind,
         bundle: T,
+        change_tick: u64,
     ) {
-        let bundle_kind = T::get_bundle_kind();
+        let archetype = self
+            .archetype_by_bundle_kind
+            .get_mut(&bundle_kind)
+            .expect("Could not find archetype of bundle");
 
-        // Ensure archetype exists
-        let archetype = {
-            let provisional_archetype = self.archetype_by_bundle_kind.get_mut(&bundle_kind);
-            if provisional_archetype.is_some() {
-                provisional_archetype.unwrap()
-            } else {
-                self.archetype_by_bundle_kind
-                    .insert(bundle_kind.clone(), Archetype::new());
-                self.archetype_by_bundle_kind
-                    .get_mut(&bundle_kind.clone())
-                    .unwrap()
+        // Push bundle into archetype
+        bundle.push_into_archetype(entity, archetype);
+        archetype.fill_ticks(change_tick);
+
+        // Record location of entity
+        let row = archetype.get_entity_count() - 1;
+        self.entities
+            .set_location(entity, EntityLocation { bundle_kind, row });
+    }
+    pub(crate) fn despawn(&mut self, entity: Entity, change_tick: u64) -> bool {
+        let location = match self.entities.get_location(entity) {
+            Some(location) => location.clone(),
+            None => return false,
+        };
+        let archetype = self
+            .archetype_by_bundle_kind
+            .get_mut(&location.bundle_kind)
+            .expect("Could not find archetype of entity location");
+        if let Some(moved_entity) = archetype.swap_remove(location.row) {
+            self.entities.set_row(moved_entity, location.row);
+        }
+        for component_kind in archetype.get_component_kinds() {
+            self.record_removal(component_kind, entity, change_tick);
+        }
+        self.entities.free(entity);
+        self.preserved_components_by_entity.remove(&entity);
+        true
+    }
+    fn record_removal(&mut self, component_kind: ComponentKind, entity: Entity, change_tick: u64) {
+        self.removals_by_component_kind
+            .entry(component_kind)
+            .or_default()
+            .push((entity, change_tick));
+    }
+    ///
+    /// Get every recorded removal of the component kind, oldest first.
+    ///
+    pub(crate) fn get_removals(&self, component_kind: &ComponentKind) -> &[(Entity, u64)] {
+        self.removals_by_component_kind
+            .get(component_kind)
+            .map_or(&[], |removals| &removals[..])
+    }
+    ///
+    /// Forget removals which happened before `tick`.
+    ///
+    pub(crate) fn clear_removals_before(&mut self, tick: u64) {
+        for removals in self.removals_by_component_kind.values_mut() {
+            removals.retain(|(_, removal_tick)| *removal_tick >= tick);
+        }
+        self.removals_by_component_kind
+            .retain(|_, removals| !removals.is_empty());
+    }
+    ///
+    /// Take over the removals of the replaced storage and record every component of its entities
+    /// as removed at `change_tick`.
+    ///
+    pub(crate) fn record_replaced_removals(&mut self, replaced: Storage, change_tick: u64) {
+        self.removals_by_component_kind = replaced.removals_by_component_kind;
+        for archetype in replaced.archetype_by_bundle_kind.values() {
+            for component_kind in archetype.get_component_kinds() {
+                self.removals_by_component_kind
+                    .entry(component_kind)
+                    .or_default()
+                    .extend(
+                        archetype
+                            .get_entity_ids()
+                            .iter()
+                            .map(|entity| (*entity, change_tick)),
+                    );
             }
+        }
+    }
+    pub(crate) fn clear_removals(&mut self) {
+        self.removals_by_component_kind.clear();
+    }
+    ///
+    /// Get the state of the entity allocator to save.
+    ///
+    pub(crate) fn get_entity_slots(&self) -> EntitySlots {
+        self.entities.get_slots()
+    }
+    ///
+    /// Restore the saved state of the entity allocator, before any entity has been restored.
+    ///
+    pub(crate) fn restore_entity_slots(
+        &mut self,
+        entity_slots: EntitySlots,
+    ) -> Result<(), TitanError> {
+        self.entities.restore_slots(entity_slots)
+    }
+    pub(crate) fn has_preserved_components(&self) -> bool {
+        !self.preserved_components_by_entity.is_empty()
+    }
+    pub(crate) fn get_preserved_components(&self, entity: Entity) -> Option<&Map<String, Value>> {
+        self.preserved_components_by_entity.get(&entity)
+    }
+    pub(crate) fn set_preserved_components(
+        &mut self,
+        entity: Entity,
+        preserved_components: Map<String, Value>,
+    ) {
+        self.preserved_components_by_entity
+            .insert(entity, preserved_components);
+    }
+    pub(crate) fn is_alive(&self, entity: Entity) -> bool {
+        self.entities.is_alive(entity)
+    }
+    pub(crate) fn get_bundle_kind(&self, entity: Entity) -> Option<&BundleKind> {
+        Some(&self.entities.get_location(entity)?.bundle_kind)
+    }
+    pub(crate) fn get_component_kinds(&self, entity: Entity) -> Option<Vec<ComponentKind>> {
+        let location = self.entities.get_location(entity)?;
+        let archetype = self.archetype_by_bundle_kind.get(&location.bundle_kind)?;
+        Some(archetype.get_component_kinds())
+    }
+    pub(crate) fn get<T: 'static + ComponentMeta>(
+        &self,
+        entity: Entity,
+    ) -> Result<Option<ComponentRef<'_, T>>, TitanError> {
+        let (archetype, row) = match self.find_component_archetype::<T>(entity) {
+            Some(found) => found,
+            None => return Ok(None),
         };
+        Ok(Some(ComponentRef {
+            component_vec: archetype.get_component_vec_lock::<T>()?,
+            row,
+        }))
+    }
+    pub(crate) fn get_mut<T: 'static + ComponentMeta>(
+        &self,
+        entity: Entity,
+        change_tick: u64,
+    ) -> Result<Option<ComponentRefMut<'_, T>>, TitanError> {
+        let (archetype, row) = match self.find_component_archetype::<T>(entity) {
+            Some(found) => found,
+            None => return Ok(None),
+        };
+        Ok(Some(ComponentRefMut {
+            component_vec: archetype.get_component_vec_lock_mut::<T>()?,
+            ticks: archetype.get_component_ticks::<T>(),
+            row,
+            change_tick,
+        }))
+    }
+    pub(crate) fn insert_component<T: 'static + ComponentMeta>(
+        &mut self,
+        entity: Entity,
+        component: T,
+        change_tick: u64,
+    ) -> Result<bool, TitanError> {
+        let location = match self.entities.get_location(entity) {
+            Some(location) => location.clone(),
+            None => return Ok(false),
+        };
+        let archetype = self
+            .archetype_by_bundle_kind
+            .get_mut(&location.bundle_kind)
+            .expect("Could not find archetype of entity location");
+        archetype.check_component_type::<T>()?;
 
-        // Push bundle into archetype
-        bundle.push_into_archetype(entity_id, archetype);
+        // Replace component in place if entity already has one of this kind
+        if archetype.has_component::<T>() {
+            archetype.get_component_vec_mut::<T>()[location.row] = component;
+            archetype.mark_changed::<T>(location.row, change_tick);
+            return Ok(true);
+        }
+
+        let mut component_kinds = archetype.get_component_kinds();
+        component_kinds.push(T::get_component_kind());
+        let target_bundle_kind = BundleKind::from_component_kinds(&component_kinds);
+        if let Some(target_archetype) = self.archetype_by_bundle_kind.get(&target_bundle_kind) {
+            target_archetype.check_component_type::<T>()?;
+        }
+        self.migrate_entity(entity, &location, &component_kinds);
+        let target_archetype = self
+            .archetype_by_bundle_kind
+            .get_mut(&target_bundle_kind)
+            .expect("Could not find archetype entity was migrated to");
+        target_archetype.push_component(component);
+        target_archetype.fill_ticks(change_tick);
+        Ok(true)
+    }
+    pub(crate) fn remove_component<T: 'static + ComponentMeta>(
+        &mut self,
+        entity: Entity,
+        change_tick: u64,
+    ) -> Result<Option<T>, TitanError> {
+        let location = match self.entities.get_location(entity) {
+            Some(location) => location.clone(),
+            None => return Ok(None),
+        };
+        let archetype = self
+            .archetype_by_bundle_kind
+            .get(&location.bundle_kind)
+            .expect("Could not find archetype of entity location");
+        if !archetype.has_component::<T>() {
+            return Ok(None);
+        }
+        archetype.check_component_type::<T>()?;
 
-        // Ensure entity_id is safe
-        if entity_id >= self.current_entity_id {
-            self.current_entity_id = entity_id + 1;
+        let component_kind = T::get_component_kind();
+        let component_kinds: Vec<_> = archetype
+            .get_component_kinds()
+            .into_iter()
+            .filter(|kind| *kind != component_kind)
+            .collect();
+        let mut removed_components = self.migrate_entity(entity, &location, &component_kinds);
+        let component = removed_components
+            .remove(&component_kind)
+            .expect("Could not find removed component")
+            .downcast::<T>()
+            .expect("Could not downcast removed component to T");
+        self.record_removal(component_kind, entity, change_tick);
+        Ok(Some(*component))
+    }
+    ///
+    /// Move the row of an entity into the archetype holding exactly `component_kinds`, creating
+    /// the archetype if needed. Components of kinds not present in the target archetype are
+    /// returned.
+    ///
+    /// Components of kinds not present in the source archetype must be pushed into the target
+    /// archetype by the caller.
+    ///
+    fn migrate_entity(
+        &mut self,
+        entity: Entity,
+        location: &EntityLocation,
+        component_kinds: &[ComponentKind],
+    ) -> HashMap<ComponentKind, Box<dyn Any>> {
+        let target_bundle_kind = BundleKind::from_component_kinds(component_kinds);
+        let mut source_archetype = self
+            .archetype_by_bundle_kind
+            .remove(&location.bundle_kind)
+            .expect("Could not find archetype of entity location");
+        let target_archetype = self
+            .archetype_by_bundle_kind
+            .entry(target_bundle_kind.clone())
+            .or_insert_with(|| source_archetype.new_empty_with(component_kinds));
+
+        let (moved_entity, removed_components) =
+            source_archetype.move_row_into(location.row, target_archetype);
+        let target_row = target_archetype.get_entity_count() - 1;
+        self.archetype_by_bundle_kind
+            .insert(location.bundle_kind.clone(), source_archetype);
+
+        // Update locations
+        if let Some(moved_entity) = moved_entity {
+            self.entities.set_row(moved_entity, location.row);
         }
+        self.entities.set_location(
+            entity,
+            EntityLocation {
+                bundle_kind: target_bundle_kind,
+                row: target_row,
+            },
+        );
+        removed_components
+    }
+    fn find_component_archetype<T: 'static + ComponentMeta>(
+        &self,
+        entity: Entity,
+    ) -> Option<(&Archetype, usize)> {
+        let location = self.entities.get_location(entity)?;
+        let archetype = self.archetype_by_bundle_kind.get(&location.bundle_kind)?;
+        if !archetype.has_component::<T>() {
+            return None;
+        }
+        Some((archetype, location.row))
+    }
+    pub(crate) fn query<'fetch, T: Query<'fetch>, F: Filter>(
+        &'fetch self,
+        last_run_tick: u64,
+        change_tick: u64,
+    ) -> Result<T::ResultType, TitanError> {
+        <T>::query::<F>(self, last_run_tick, change_tick)
+    }
+}
+
+///
+/// Type erased operations on a component vec lock. Allows an `Archetype` to manipulate whole rows
+/// without knowing the concrete component type of each column.
+///
+pub(crate) type NewComponentVecFn = fn() -> Box<dyn ComponentVec>;
+
+pub(crate) fn new_component_vec<T: 'static + Send + Sync>() -> Box<dyn ComponentVec> {
+    Box::new(RwLock::new(Vec::<T>::new()))
+}
+
+///
+/// Type erased component together with the constructor of the component vec it belongs in.
+///
+pub(crate) struct BoxedComponent {
+    pub(crate) component_kind: ComponentKind,
+    pub(crate) component: Box<dyn Any>,
+    pub(crate) new_component_vec_fn: NewComponentVecFn,
+}
+
+pub(crate) trait ComponentVec: Send + Sync {
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+    fn new_empty(&self) -> Box<dyn ComponentVec>;
+    fn push_boxed(&mut self, component: Box<dyn Any>);
+    fn swap_remove(&mut self, index: usize);
+    fn swap_remove_boxed(&mut self, index: usize) -> Box<dyn Any>;
+    ///
+    /// Swap remove the component at `index` and push it into `target`, which must be a component
+    /// vec of the same type.
+    ///
+    fn swap_remove_into(&mut self, index: usize, target: &mut dyn ComponentVec);
+    ///
+    /// Move all components into `target`, which must be a component vec of the same type.
+    ///
+    fn append_into(&mut self, target: &mut dyn ComponentVec);
+    fn get_component_count(&mut self) -> usize;
+}
+impl<T: 'static + Send + Sync> ComponentVec for RwLock<Vec<T>> {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+    fn new_empty(&self) -> Box<dyn ComponentVec> {
+        new_component_vec::<T>()
+    }
+    fn push_boxed(&mut self, component: Box<dyn Any>) {
+        let component = component
+            .downcast::<T>()
+            .expect("Could not downcast boxed component to T");
+        self.get_mut()
+            .expect("Could not get write access to component vec in order to push component")
+            .push(*component);
+    }
+    fn swap_remove(&mut self, index: usize) {
+        self.get_mut()
+            .expect("Could not get write access to component vec in order to remove component")
+            .swap_remove(index);
+    }
+    fn swap_remove_boxed(&mut self, index: usize) -> Box<dyn Any> {
+        let component = self
+            .get_mut()
+            .expect("Could not get write access to component vec in order to remove component")
+            .swap_remove(index);
+        Box::new(component)
+    }
+    fn swap_remove_into(&mut self, index: usize, target: &mut dyn ComponentVec) {
+        let component = self
+            .get_mut()
+            .expect("Could not get write access to component vec in order to remove component")
+            .swap_remove(index);
+        target
+            .as_any_mut()
+            .downcast_mut::<RwLock<Vec<T>>>()
+            .expect("Could not downcast target component vec to Vec<T>")
+            .get_mut()
+            .expect("Could not get write access to component vec in order to push component")
+            .push(component);
+    }
+    fn append_into(&mut self, target: &mut dyn ComponentVec) {
+        let component_vec = self
+            .get_mut()
+            .expect("Could not get write access to component vec in order to move components");
+        target
+            .as_any_mut()
+            .downcast_mut::<RwLock<Vec<T>>>()
+            .expect("Could not downcast target component vec to Vec<T>")
+            .get_mut()
+            .expect("Could not get write access to component vec in order to push components")
+            .append(component_vec);
+    }
+    fn get_component_count(&mut self) -> usize {
+        self.get_mut()
+            .expect("Could not get write access to component vec in order to count components")
+            .len()
+    }
+}
+
+///
+/// Ticks at which the components of a component vec were added and last changed, one per row.
+/// Changed ticks are atomic so they can be recorded while the archetype is shared by queries.
+///
+#[derive(Default)]
+pub(crate) struct ComponentTicks {
+    added: Vec<u64>,
+    changed: Vec<AtomicU64>,
+}
+impl ComponentTicks {
+    fn len(&self) -> usize {
+        self.added.len()
+    }
+    fn push(&mut self, added: u64, changed: u64) {
+        self.added.push(added);
+        self.changed.push(AtomicU64::new(changed));
+    }
+    fn swap_remove(&mut self, index: usize) -> (u64, u64) {
+        let added = self.added.swap_remove(index);
+        let changed = self.changed.swap_remove(index).into_inner();
+        (added, changed)
+    }
+    fn append(&mut self, other: &mut ComponentTicks) {
+        self.added.append(&mut other.added);
+        self.changed.append(&mut other.changed);
     }
-    pub(crate) fn query<'fetch, T: Query<'fetch>>(&'fetch self) -> T::ResultType {
-        <T>::query(self)
+    pub(crate) fn is_added_since(&self, row: usize, last_run_tick: u64) -> bool {
+        self.added
+            .get(row)
+            .is_some_and(|added| *added > last_run_tick)
+    }
+    pub(crate) fn is_changed_since(&self, row: usize, last_run_tick: u64) -> bool {
+        self.changed
+            .get(row)
+            .is_some_and(|changed| changed.load(Ordering::Relaxed) > last_run_tick)
+    }
+    pub(crate) fn set_changed(&self, row: usize, change_tick: u64) {
+        if let Some(changed) = self.changed.get(row) {
+            changed.store(change_tick, Ordering::Relaxed);
+        }
     }
 }
+
 pub struct Archetype {
-    entity_ids: Vec<EntityId>,
-    component_vec_locks_by_component_kind: HashMap<ComponentKind, Box<dyn Any>>,
+    entity_ids: Vec<Entity>,
+    component_vec_locks_by_component_kind: HashMap<ComponentKind, Box<dyn ComponentVec>>,
+    ticks_by_component_kind: HashMap<ComponentKind, ComponentTicks>,
 }
 impl Archetype {
     fn new() -> Self {
         Archetype {
             entity_ids: Vec::new(),
             component_vec_locks_by_component_kind: HashMap::new(),
+            ticks_by_component_kind: HashMap::new(),
+        }
+    }
+    ///
+    /// Build an archetype from whole component vecs, which must each hold one component per
+    /// entity.
+    ///
+    pub(crate) fn from_component_vecs(
+        entity_ids: Vec<Entity>,
+        component_vec_locks_by_component_kind: HashMap<ComponentKind, Box<dyn ComponentVec>>,
+    ) -> Result<Archetype, TitanError> {
+        let mut archetype = Archetype {
+            entity_ids,
+            component_vec_locks_by_component_kind,
+            ticks_by_component_kind: HashMap::new(),
+        };
+        let entity_count = archetype.get_entity_count();
+        for (component_kind, component_vec_lock) in
+            archetype.component_vec_locks_by_component_kind.iter_mut()
+        {
+            if component_vec_lock.get_component_count() != entity_count {
+                return Err(TitanError::MalformedSaveData(format!(
+                    "Component vec of component_kind {:?} does not hold one component per entity",
+                    component_kind.0
+                )));
+            }
         }
+        Ok(archetype)
     }
     pub fn get_entity_count(&self) -> usize {
         self.entity_ids.len()
     }
+    pub(crate) fn get_component_kinds(&self) -> Vec<ComponentKind> {
+        self.component_vec_locks_by_component_kind
+            .keys()
+            .cloned()
+            .collect()
+    }
+    ///
+    /// Create an empty archetype with component vecs of the same type as this archetype for each
+    /// of the given component kinds which this archetype has.
+    ///
+    fn new_empty_with(&self, component_kinds: &[ComponentKind]) -> Archetype {
+        let mut archetype = Archetype::new();
+        for component_kind in component_kinds {
+            if let Some(component_vec_lock) = self
+                .component_vec_locks_by_component_kind
+                .get(component_kind)
+            {
+                archetype
+                    .component_vec_locks_by_component_kind
+                    .insert(component_kind.clone(), component_vec_lock.new_empty());
+            }
+        }
+        archetype
+    }
     pub(crate) fn has_component<T: 'static + ComponentMeta>(&self) -> bool {
         let component_kind = T::get_component_kind();
         self.component_vec_locks_by_component_kind
             .contains_key(&component_kind)
     }
+    ///
+    /// Check that the component vec of the kind of `T`, if any, holds components of type `T`.
+    /// Returns an error if the component kind is used by a different type in this archetype.
+    ///
+    pub(crate) fn check_component_type<T: 'static + ComponentMeta>(
+        &self,
+    ) -> Result<(), TitanError> {
+        let component_kind = T::get_component_kind();
+        match self
+            .component_vec_locks_by_component_kind
+            .get(&component_kind)
+        {
+            Some(component_vec_lock) if !component_vec_lock.as_any().is::<RwLock<Vec<T>>>() => {
+                Err(TitanError::ComponentKindCollision(component_kind))
+            }
+            _ => Ok(()),
+        }
+    }
     pub(crate) fn push_component<T: 'static + ComponentMeta>(&mut self, component: T) {
         let component_kind = T::get_component_kind();
         if !self
@@ -92,6 +793,7 @@ impl Archetype {
             .component_vec_locks_by_component_kind
             .get_mut(&component_kind)
             .unwrap()
+            .as_any_mut()
             .downcast_mut::<RwLock<Vec<T>>>()
             .expect("Could not downcast component vec to Vec<T>");
         component_vec
@@ -99,34 +801,268 @@ impl Archetype {
             .expect("Could not get write access to component vec in order to push new component")
             .push(component);
     }
-    pub(crate) fn push_entity_id(&mut self, entity_id: EntityId) {
-        self.entity_ids.push(entity_id);
+    pub(crate) fn push_boxed_component(&mut self, boxed_component: BoxedComponent) {
+        self.component_vec_locks_by_component_kind
+            .entry(boxed_component.component_kind)
+            .or_insert_with(boxed_component.new_component_vec_fn)
+            .push_boxed(boxed_component.component);
+    }
+    pub(crate) fn push_entity_id(&mut self, entity: Entity) {
+        self.entity_ids.push(entity);
     }
-    pub(crate) fn get_entity_id_at_index_unchecked(&self, index: usize) -> EntityId {
+    pub(crate) fn get_entity_ids(&self) -> &[Entity] {
+        &self.entity_ids
+    }
+    pub(crate) fn get_entity_id_at_index_unchecked(&self, index: usize) -> Entity {
         self.entity_ids[index]
     }
+    ///
+    /// Record every row which has no ticks yet in each component vec as added and changed at
+    /// `change_tick`. Rows without ticks are always the last rows of a component vec.
+    ///
+    fn fill_ticks(&mut self, change_tick: u64) {
+        let entity_count = self.get_entity_count();
+        for component_kind in self.component_vec_locks_by_component_kind.keys() {
+            let ticks = self
+                .ticks_by_component_kind
+                .entry(component_kind.clone())
+                .or_default();
+            while ticks.len() < entity_count {
+                ticks.push(change_tick, change_tick);
+            }
+        }
+    }
+    pub(crate) fn get_component_ticks<T: 'static + ComponentMeta>(
+        &self,
+    ) -> Option<&ComponentTicks> {
+        self.ticks_by_component_kind.get(&T::get_component_kind())
+    }
+    ///
+    /// Record the component `T` at `row` as changed at `change_tick`.
+    ///
+    pub(crate) fn mark_changed<T: 'static + ComponentMeta>(&self, row: usize, change_tick: u64) {
+        if let Some(ticks) = self.get_component_ticks::<T>() {
+            ticks.set_changed(row, change_tick);
+        }
+    }
+    ///
+    /// Remove the entity at `index` by swapping the last entity into its place, in the
+    /// `entity_ids` and every component vec, keeping all rows aligned.
+    ///
+    /// Returns the entity which was moved into `index`, if any.
+    ///
+    pub(crate) fn swap_remove(&mut self, index: usize) -> Option<Entity> {
+        self.entity_ids.swap_remove(index);
+        for component_vec_lock in self.component_vec_locks_by_component_kind.values_mut() {
+            component_vec_lock.swap_remove(index);
+        }
+        for ticks in self.ticks_by_component_kind.values_mut() {
+            ticks.swap_remove(index);
+        }
+        self.entity_ids.get(index).copied()
+    }
+    ///
+    /// Move the entity at `index` into `target`, swap removing it from this archetype. Components
+    /// of kinds which `target` does not have are returned instead.
+    ///
+    /// Returns the entity which was moved into `index`, if any.
+    ///
+    fn move_row_into(
+        &mut self,
+        index: usize,
+        target: &mut Archetype,
+    ) -> (Option<Entity>, HashMap<ComponentKind, Box<dyn Any>>) {
+        let entity = self.entity_ids.swap_remove(index);
+        target.entity_ids.push(entity);
+        let mut removed_components = HashMap::new();
+        for (component_kind, component_vec_lock) in
+            self.component_vec_locks_by_component_kind.iter_mut()
+        {
+            match target
+                .component_vec_locks_by_component_kind
+                .get_mut(component_kind)
+            {
+                Some(target_component_vec_lock) => {
+                    component_vec_lock.swap_remove_into(index, target_component_vec_lock.as_mut())
+                }
+                None => {
+                    removed_components.insert(
+                        component_kind.clone(),
+                        component_vec_lock.swap_remove_boxed(index),
+                    );
+                }
+            }
+        }
+
+        // Components keep their ticks when moved
+        for (component_kind, ticks) in self.ticks_by_component_kind.iter_mut() {
+            let (added, changed) = ticks.swap_remove(index);
+            if target
+                .component_vec_locks_by_component_kind
+                .contains_key(component_kind)
+            {
+                target
+                    .ticks_by_component_kind
+                    .entry(component_kind.clone())
+                    .or_default()
+                    .push(added, changed);
+            }
+        }
+        (self.entity_ids.get(index).copied(), removed_components)
+    }
+    ///
+    /// Move all rows of `other`, which must have the same component kinds, to the end of this
+    /// archetype under the given entity ids.
+    ///
+    fn append(&mut self, mut other: Archetype, entity_ids: &[Entity]) {
+        self.entity_ids.extend_from_slice(entity_ids);
+        for (component_kind, component_vec_lock) in
+            other.component_vec_locks_by_component_kind.iter_mut()
+        {
+            let target_component_vec_lock = self
+                .component_vec_locks_by_component_kind
+                .get_mut(component_kind)
+                .expect("Could not find component vec of appended archetype");
+            component_vec_lock.append_into(target_component_vec_lock.as_mut());
+        }
+        for (component_kind, ticks) in other.ticks_by_component_kind.iter_mut() {
+            self.ticks_by_component_kind
+                .entry(component_kind.clone())
+                .or_default()
+                .append(ticks);
+        }
+    }
     pub(crate) fn get_component_vec_lock<T: 'static + ComponentMeta>(
         &self,
-    ) -> RwLockReadGuard<Vec<T>> {
+    ) -> Result<RwLockReadGuard<'_, Vec<T>>, TitanError> {
+        let component_kind = T::get_component_kind();
+        self.get_component_vec_rwlock::<T>()?
+            .try_read()
+            .map_err(|error| map_try_lock_error(error, component_kind))
+    }
+    pub(crate) fn get_component_vec_lock_mut<T: 'static + ComponentMeta>(
+        &self,
+    ) -> Result<RwLockWriteGuard<'_, Vec<T>>, TitanError> {
+        let component_kind = T::get_component_kind();
+        self.get_component_vec_rwlock::<T>()?
+            .try_write()
+            .map_err(|error| map_try_lock_error(error, component_kind))
+    }
+    ///
+    /// Get the component vec without locking, given exclusive access to the archetype.
+    ///
+    pub(crate) fn get_component_vec_mut<T: 'static + ComponentMeta>(&mut self) -> &mut Vec<T> {
         let component_kind = T::get_component_kind();
         self.component_vec_locks_by_component_kind
-            .get(&component_kind)
+            .get_mut(&component_kind)
             .expect("Could not find component vec for given component_kind in archetype")
-            .downcast_ref::<RwLock<Vec<T>>>()
+            .as_any_mut()
+            .downcast_mut::<RwLock<Vec<T>>>()
             .expect("Could not downcast to lock of component vec")
-            .try_read()
-            .expect("Could not get from component vec lock")
+            .get_mut()
+            .expect("Could not get write access to component vec")
     }
-    pub(crate) fn get_component_vec_lock_mut<T: 'static + ComponentMeta>(
+    fn get_component_vec_rwlock<T: 'static + ComponentMeta>(
         &self,
-    ) -> RwLockWriteGuard<Vec<T>> {
+    ) -> Result<&RwLock<Vec<T>>, TitanError> {
         let component_kind = T::get_component_kind();
         self.component_vec_locks_by_component_kind
             .get(&component_kind)
             .expect("Could not find component vec for given component_kind in archetype")
+            .as_any()
             .downcast_ref::<RwLock<Vec<T>>>()
-            .expect("Could not downcast to lock of component vec")
-            .try_write()
-            .expect("Could not get from component vec lock")
+            .ok_or(TitanError::ComponentKindCollision(component_kind))
+    }
+}
+
+fn map_try_lock_error<G>(error: TryLockError<G>, component_kind: ComponentKind) -> TitanError {
+    match error {
+        TryLockError::WouldBlock => TitanError::LockConflict(component_kind),
+        TryLockError::Poisoned(_) => TitanError::LockPoisoned(component_kind),
+    }
+}
+
+///
+/// Read access to a single component of an entity. Holds the read lock of the component vec
+/// containing the component.
+///
+pub struct ComponentRef<'a, T> {
+    component_vec: RwLockReadGuard<'a, Vec<T>>,
+    row: usize,
+}
+impl<'a, T> Deref for ComponentRef<'a, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.component_vec[self.row]
+    }
+}
+
+///
+/// Write access to a single component of an entity. Holds the write lock of the component vec
+/// containing the component, which is recorded as changed when dereferenced mutably.
+///
+pub struct ComponentRefMut<'a, T> {
+    component_vec: RwLockWriteGuard<'a, Vec<T>>,
+    ticks: Option<&'a ComponentTicks>,
+    row: usize,
+    change_tick: u64,
+}
+impl<'a, T> Deref for ComponentRefMut<'a, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.component_vec[self.row]
+    }
+}
+impl<'a, T> DerefMut for ComponentRefMut<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        if let Some(ticks) = self.ticks {
+            ticks.set_changed(self.row, self.change_tick);
+        }
+        &mut self.component_vec[self.row]
+    }
+}
+
+///
+/// Entities which had the component `T` removed, including by despawning, after a given tick.
+///
+pub struct RemovedComponents<'a, T> {
+    pub(crate) removals: &'a [(Entity, u64)],
+    pub(crate) last_run_tick: u64,
+    pub(crate) phantom: PhantomData<T>,
+}
+impl<'a, T> RemovedComponents<'a, T> {
+    ///
+    /// Iterate the entities in order of removal. An entity may be yielded more than once if the
+    /// component was inserted and removed again.
+    ///
+    pub fn iter(&self) -> impl Iterator<Item = Entity> + 'a {
+        let last_run_tick = self.last_run_tick;
+        self.removals
+            .iter()
+            .filter(move |(_, removal_tick)| *removal_tick > last_run_tick)
+            .map(|(entity, _)| *entity)
+    }
+    pub fn is_empty(&self) -> bool {
+        self.iter().next().is_none()
+    }
+}
+
+///
+/// When removals are forgotten as the world tick advances.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RemovalClearPolicy {
+    ///
+    /// Keep removals of the current tick and of the given number of ended ticks.
+    ///
+    KeepTicks(u32),
+    ///
+    /// Keep removals until `ECS::clear_removed_components` is called.
+    ///
+    Manual,
+}
+impl Default for RemovalClearPolicy {
+    fn default() -> Self {
+        RemovalClearPolicy::KeepTicks(1)
     }
 }
@@ -0,0 +1,268 @@
+use crate::query::{Filter, Query, ResultIter};
+use crate::{
+    ComponentKind, ComponentMeta, RemovedComponents, ResourceRef, ResourceRefMut, TitanError, ECS,
+};
+use paste::paste;
+use std::{
+    any::{type_name, TypeId},
+    collections::HashSet,
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+};
+
+///
+/// Component kinds and resources a system reads and writes. Systems whose accesses are
+/// compatible take no conflicting locks, so a parallel `Schedule` may run them at the same time.
+///
+#[derive(Debug, Default, Clone)]
+pub struct Access {
+    reads: HashSet<ComponentKind>,
+    writes: HashSet<ComponentKind>,
+    resource_reads: HashSet<TypeId>,
+    resource_writes: HashSet<TypeId>,
+    exclusive: bool,
+}
+impl Access {
+    ///
+    /// Access to the whole ECS, compatible with no other access.
+    ///
+    pub fn exclusive() -> Self {
+        Access {
+            exclusive: true,
+            ..Access::default()
+        }
+    }
+    pub fn add_read(&mut self, component_kind: ComponentKind) {
+        self.reads.insert(component_kind);
+    }
+    pub fn add_write(&mut self, component_kind: ComponentKind) {
+        self.writes.insert(component_kind);
+    }
+    pub fn add_resource_read<R: 'static>(&mut self) {
+        self.resource_reads.insert(TypeId::of::<R>());
+    }
+    pub fn add_resource_write<R: 'static>(&mut self) {
+        self.resource_writes.insert(TypeId::of::<R>());
+    }
+    ///
+    /// Whether neither access writes what the other reads or writes.
+    ///
+    pub fn is_compatible(&self, other: &Access) -> bool {
+        !self.exclusive
+            && !other.exclusive
+            && self.writes.is_disjoint(&other.reads)
+            && self.writes.is_disjoint(&other.writes)
+            && self.reads.is_disjoint(&other.writes)
+            && self.resource_writes.is_disjoint(&other.resource_reads)
+            && self.resource_writes.is_disjoint(&other.resource_writes)
+            && self.resource_reads.is_disjoint(&other.resource_writes)
+    }
+}
+
+///
+/// Logic run against the ECS by a `Schedule`. Implemented for plain functions taking
+/// `SystemParam`s, such as `fn movement(query: SystemQuery<(&mut Position, &Velocity)>)`.
+///
+pub trait System: Send {
+    ///
+    /// Run the system. `Added` and `Changed` match components added or changed after
+    /// `last_run_tick`, the tick at which the system last ran.
+    ///
+    fn run(&mut self, ecs: &ECS, last_run_tick: u64) -> Result<(), TitanError>;
+    ///
+    /// Component kinds and resources the system accesses. Defaults to exclusive access, so the
+    /// system never runs at the same time as another system.
+    ///
+    fn access(&self) -> Access {
+        Access::exclusive()
+    }
+}
+
+///
+/// Conversion into a `System`, implemented for functions taking up to 8 `SystemParam`s and for
+/// every `System`.
+///
+pub trait IntoSystem<Params> {
+    type System: System + 'static;
+    fn into_system(self) -> Self::System;
+}
+
+///
+/// Marker for the `IntoSystem` implementation of types which are already a `System`.
+///
+pub struct IsSystem;
+impl<S: System + 'static> IntoSystem<IsSystem> for S {
+    type System = S;
+    fn into_system(self) -> S {
+        self
+    }
+}
+
+///
+/// Parameter of a function system, fetched from the ECS each time the system runs.
+///
+pub trait SystemParam {
+    type Item<'w>;
+    fn fetch<'w>(ecs: &'w ECS, last_run_tick: u64) -> Result<Self::Item<'w>, TitanError>;
+    fn add_access(access: &mut Access);
+}
+
+///
+/// Query of a function system. Dereferences to the result of the query, so components are
+/// iterated with `result_iter` like the result of `ECS::query`. Entities are further narrowed
+/// down by the filter `F`, like with `ECS::query_filtered`.
+///
+pub struct SystemQuery<'w, Q: Query<'w>, F: Filter = ()> {
+    result: Q::ResultType,
+    phantom: PhantomData<fn() -> F>,
+}
+impl<'w, Q: Query<'w>, F: Filter> Deref for SystemQuery<'w, Q, F> {
+    type Target = Q::ResultType;
+    fn deref(&self) -> &Q::ResultType {
+        &self.result
+    }
+}
+impl<'w, Q: Query<'w>, F: Filter> DerefMut for SystemQuery<'w, Q, F> {
+    fn deref_mut(&mut self) -> &mut Q::ResultType {
+        &mut self.result
+    }
+}
+impl<'w, 'borrow, Q: Query<'w>, F: Filter> ResultIter<'borrow> for SystemQuery<'w, Q, F>
+where
+    Q::ResultType: ResultIter<'borrow>,
+{
+    type IterType = <Q::ResultType as ResultIter<'borrow>>::IterType;
+    fn result_iter(&'borrow mut self) -> Self::IterType {
+        self.result.result_iter()
+    }
+}
+
+///
+/// Implementations for `SystemParam` for queries.
+///
+impl<'a, Q, F> SystemParam for SystemQuery<'a, Q, F>
+where
+    Q: for<'w> Query<'w>,
+    F: Filter,
+{
+    type Item<'w> = SystemQuery<'w, Q, F>;
+    fn fetch<'w>(ecs: &'w ECS, last_run_tick: u64) -> Result<Self::Item<'w>, TitanError> {
+        Ok(SystemQuery {
+            result: ecs.try_query_filtered_since::<Q, F>(last_run_tick)?,
+            phantom: PhantomData,
+        })
+    }
+    fn add_access(access: &mut Access) {
+        Q::add_access(access);
+        F::add_access(access);
+    }
+}
+///
+/// Implementations for `SystemParam` for read access to a resource. The system fails if there is
+/// no resource of the type.
+///
+impl<'a, R: 'static + Send + Sync> SystemParam for ResourceRef<'a, R> {
+    type Item<'w> = ResourceRef<'w, R>;
+    fn fetch<'w>(ecs: &'w ECS, _last_run_tick: u64) -> Result<Self::Item<'w>, TitanError> {
+        ecs.try_resource::<R>()?
+            .ok_or_else(|| TitanError::ResourceNotFound(type_name::<R>().to_string()))
+    }
+    fn add_access(access: &mut Access) {
+        access.add_resource_read::<R>();
+    }
+}
+///
+/// Implementations for `SystemParam` for write access to a resource. The system fails if there is
+/// no resource of the type.
+///
+impl<'a, R: 'static + Send + Sync> SystemParam for ResourceRefMut<'a, R> {
+    type Item<'w> = ResourceRefMut<'w, R>;
+    fn fetch<'w>(ecs: &'w ECS, _last_run_tick: u64) -> Result<Self::Item<'w>, TitanError> {
+        ecs.try_resource_mut::<R>()?
+            .ok_or_else(|| TitanError::ResourceNotFound(type_name::<R>().to_string()))
+    }
+    fn add_access(access: &mut Access) {
+        access.add_resource_write::<R>();
+    }
+}
+///
+/// Implementations for `SystemParam` for removed components, yielding the entities which had
+/// the component removed since the system last ran.
+///
+impl<'a, T: 'static + ComponentMeta> SystemParam for RemovedComponents<'a, T> {
+    type Item<'w> = RemovedComponents<'w, T>;
+    fn fetch<'w>(ecs: &'w ECS, last_run_tick: u64) -> Result<Self::Item<'w>, TitanError> {
+        Ok(ecs.removed_components_since::<T>(last_run_tick))
+    }
+    fn add_access(_access: &mut Access) {}
+}
+
+///
+/// `System` running a function with the parameters `Params`.
+///
+pub struct FunctionSystem<Func, Params> {
+    function: Func,
+    phantom: PhantomData<fn() -> Params>,
+}
+
+//
+//
+// Macros for generating parameter count specific implementations.
+//
+//
+
+///
+/// Implementations of `IntoSystem` and `System` for functions taking `SystemParam`s.
+///
+macro_rules! function_system_impl {
+    ($($name:ident),*) => {
+        paste!{
+            impl<Func, $($name),*> IntoSystem<($($name,)*)> for Func
+            where
+                Func: 'static + Send + FnMut($($name),*) + for<'w> FnMut($($name::Item<'w>),*),
+                $($name: 'static + SystemParam),*
+            {
+                type System = FunctionSystem<Func, ($($name,)*)>;
+                fn into_system(self) -> Self::System {
+                    FunctionSystem {
+                        function: self,
+                        phantom: PhantomData,
+                    }
+                }
+            }
+            impl<Func, $($name),*> System for FunctionSystem<Func, ($($name,)*)>
+            where
+                Func: 'static + Send + FnMut($($name),*) + for<'w> FnMut($($name::Item<'w>),*),
+                $($name: 'static + SystemParam),*
+            {
+                #[allow(unused_variables)]
+                fn run(&mut self, ecs: &ECS, last_run_tick: u64) -> Result<(), TitanError> {
+                    // Call through a function taking the fetched items, so the compiler picks
+                    // the higher ranked `FnMut` bound
+                    #[allow(clippy::too_many_arguments)]
+                    fn call<$($name),*>(mut function: impl FnMut($($name),*), $([<$name:lower>]: $name),*) {
+                        function($([<$name:lower>]),*)
+                    }
+                    $(let [<$name:lower>] = $name::fetch(ecs, last_run_tick)?;)*
+                    call(&mut self.function, $([<$name:lower>]),*);
+                    Ok(())
+                }
+                #[allow(unused_mut)]
+                fn access(&self) -> Access {
+                    let mut access = Access::default();
+                    $(<$name>::add_access(&mut access);)*
+                    access
+                }
+            }
+        }
+    };
+}
+function_system_impl!();
+function_system_impl!(A);
+function_system_impl!(A, B);
+function_system_impl!(A, B, C);
+function_system_impl!(A, B, C, D);
+function_system_impl!(A, B, C, D, E);
+function_system_impl!(A, B, C, D, E, F);
+function_system_impl!(A, B, C, D, E, F, G);
+function_system_impl!(A, B, C, D, E, F, G, H);
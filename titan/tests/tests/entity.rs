@@ -0,0 +1,207 @@
+use titan::*;
+use titan_macros::component;
+
+#[test]
+fn despawn() {
+    #[component]
+    #[derive(PartialEq)]
+    struct Age(u8);
+    #[component]
+    #[derive(PartialEq)]
+    struct Height(u8);
+
+    let mut ecs = ECS::default();
+    let entity = ecs.spawn_bundle((Age(10), Height(100)));
+    ecs.spawn_bundle((Age(20), Height(200)));
+    ecs.spawn_bundle((Age(30), Height(250)));
+
+    assert!(ecs.despawn(entity));
+    assert!(!ecs.despawn(entity));
+
+    let mut result = ecs.query::<(&Age, &Height)>();
+    let result: Vec<_> = result.result_iter().collect();
+
+    assert!(result.contains(&(&Age(20), &Height(200))));
+    assert!(result.contains(&(&Age(30), &Height(250))));
+    assert_eq!(result.len(), 2);
+}
+
+#[test]
+fn stale_handles() {
+    #[component]
+    struct Age(u8);
+
+    let mut ecs = ECS::default();
+    let entity_a = ecs.spawn_bundle((Age(10),));
+    ecs.despawn(entity_a);
+    let entity_b = ecs.spawn_bundle((Age(20),));
+
+    assert_eq!(entity_a.index(), entity_b.index());
+    assert_ne!(entity_a, entity_b);
+    assert!(!ecs.is_alive(entity_a));
+    assert!(ecs.is_alive(entity_b));
+    assert!(!ecs.despawn(entity_a));
+    assert!(ecs.is_alive(entity_b));
+}
+
+#[test]
+fn generations_survive_serialization() {
+    #[component]
+    struct Age(u8);
+
+    let mut ecs = ECS::default();
+    ecs.register_component::<Age>();
+    ecs.register_archetype::<(Age,)>();
+    let entity_a = ecs.spawn_bundle((Age(10),));
+    ecs.despawn(entity_a);
+    let entity_b = ecs.spawn_bundle((Age(20),));
+
+    let mut ecs_2 = ECS::default();
+    ecs_2.register_component::<Age>();
+    ecs_2.register_archetype::<(Age,)>();
+    ecs_2.deserialize(&ecs.serialize());
+
+    assert!(!ecs_2.is_alive(entity_a));
+    assert!(ecs_2.is_alive(entity_b));
+    let entity_c = ecs_2.spawn_bundle((Age(30),));
+    assert_ne!(entity_c.index(), entity_b.index());
+}
+
+#[test]
+fn get_component() {
+    #[component]
+    #[derive(PartialEq)]
+    struct Age(u8);
+    #[component]
+    #[derive(PartialEq)]
+    struct Height(u8);
+
+    let mut ecs = ECS::default();
+    let entity_a = ecs.spawn_bundle((Age(10), Height(100)));
+    let entity_b = ecs.spawn_bundle((Age(20),));
+    let entity_c = ecs.spawn_bundle((Age(30), Height(250)));
+
+    // Despawning moves entity_c into the row of entity_a
+    ecs.despawn(entity_a);
+
+    assert!(ecs.get::<Age>(entity_a).is_none());
+    assert!(ecs.get::<Height>(entity_b).is_none());
+    assert_eq!(*ecs.get::<Age>(entity_b).unwrap(), Age(20));
+    assert_eq!(*ecs.get::<Height>(entity_c).unwrap(), Height(250));
+
+    ecs.get_mut::<Age>(entity_c).unwrap().0 = 31;
+    assert_eq!(*ecs.get::<Age>(entity_c).unwrap(), Age(31));
+}
+
+#[test]
+fn insert_and_remove_component() {
+    #[component]
+    #[derive(PartialEq)]
+    struct Age(u8);
+    #[component]
+    #[derive(PartialEq)]
+    struct Height(u8);
+    #[component]
+    #[derive(PartialEq)]
+    struct Burning(u8);
+
+    let mut ecs = ECS::default();
+    let entity_a = ecs.spawn_bundle((Age(10), Height(100)));
+    let entity_b = ecs.spawn_bundle((Age(20), Height(200)));
+    let entity_c = ecs.spawn_bundle((Age(30), Height(250)));
+
+    assert!(ecs.insert_component(entity_a, Burning(5)));
+    assert!(ecs.insert_component(entity_b, Burning(6)));
+    assert!(ecs.insert_component(entity_b, Burning(7)));
+
+    {
+        let mut result = ecs.query::<(&Age, &Height, &Burning)>();
+        let result: Vec<_> = result.result_iter().collect();
+        assert!(result.contains(&(&Age(10), &Height(100), &Burning(5))));
+        assert!(result.contains(&(&Age(20), &Height(200), &Burning(7))));
+        assert_eq!(result.len(), 2);
+    }
+    assert_eq!(*ecs.get::<Age>(entity_c).unwrap(), Age(30));
+
+    assert_eq!(ecs.remove_component::<Burning>(entity_a), Some(Burning(5)));
+    assert_eq!(ecs.remove_component::<Burning>(entity_a), None);
+    assert_eq!(ecs.remove_component::<Height>(entity_b), Some(Height(200)));
+
+    let mut result = ecs.query::<(&Age, &Height)>();
+    let result: Vec<_> = result.result_iter().collect();
+    assert!(result.contains(&(&Age(10), &Height(100))));
+    assert!(result.contains(&(&Age(30), &Height(250))));
+    assert_eq!(result.len(), 2);
+    assert_eq!(*ecs.get::<Burning>(entity_b).unwrap(), Burning(7));
+    assert!(ecs.get::<Height>(entity_b).is_none());
+}
+
+#[test]
+fn removed_components() {
+    #[component]
+    struct Collider(u8);
+    #[component]
+    struct Health(u8);
+
+    let mut ecs = ECS::default();
+    ecs.register_component::<Collider>();
+    ecs.register_component::<Health>();
+    let despawned = ecs.spawn_bundle((Collider(1), Health(10)));
+    let stripped = ecs.spawn_bundle((Collider(2), Health(20)));
+    let untouched = ecs.spawn_bundle((Collider(3),));
+
+    // Despawning removes every component of the entity
+    let last_run_tick = ecs.increment_change_tick();
+    ecs.despawn(despawned);
+    ecs.remove_component::<Collider>(stripped);
+    let removed: Vec<_> = ecs.removed_components::<Collider>().iter().collect();
+    assert_eq!(removed, vec![despawned, stripped]);
+    let removed: Vec<_> = ecs.removed_components::<Health>().iter().collect();
+    assert_eq!(removed, vec![despawned]);
+    assert!(!removed.contains(&untouched));
+
+    // Removals stay readable for the tick after they happened by default
+    ecs.increment_change_tick();
+    assert!(ecs.removed_components::<Collider>().is_empty());
+    let removed = ecs.removed_components_since::<Collider>(last_run_tick);
+    assert_eq!(removed.iter().count(), 2);
+    ecs.increment_change_tick();
+    let removed = ecs.removed_components_since::<Collider>(last_run_tick);
+    assert!(removed.is_empty());
+
+    // Manual clearing keeps removals until cleared
+    ecs.set_removal_clear_policy(RemovalClearPolicy::Manual);
+    let last_run_tick = ecs.increment_change_tick();
+    ecs.despawn(untouched);
+    for _ in 0..3 {
+        ecs.increment_change_tick();
+    }
+    let removed: Vec<_> = ecs
+        .removed_components_since::<Collider>(last_run_tick)
+        .iter()
+        .collect();
+    assert_eq!(removed, vec![untouched]);
+    ecs.clear_removed_components();
+    assert!(ecs
+        .removed_components_since::<Collider>(last_run_tick)
+        .is_empty());
+
+    // Deserializing removes every component of the replaced entities and keeps earlier removals
+    let ecs_serial = ecs.serialize();
+    let last_run_tick = ecs.increment_change_tick();
+    let replaced = ecs.spawn_bundle((Collider(4),));
+    ecs.despawn(stripped);
+    ecs.deserialize(&ecs_serial);
+    assert!(ecs.is_alive(stripped));
+    assert!(!ecs.is_alive(replaced));
+    let removed: Vec<_> = ecs
+        .removed_components_since::<Collider>(last_run_tick)
+        .iter()
+        .collect();
+    assert_eq!(removed, vec![replaced]);
+    let removed: Vec<_> = ecs
+        .removed_components_since::<Health>(last_run_tick)
+        .iter()
+        .collect();
+    assert_eq!(removed, vec![stripped]);
+}
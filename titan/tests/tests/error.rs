@@ -0,0 +1,136 @@
+use titan::*;
+use titan_macros::component;
+
+#[test]
+fn malformed_save_data() {
+    let mut ecs = ECS::default();
+
+    let result = ecs.try_deserialize("[{\"bundle_kind\": 4}]");
+    assert!(matches!(result, Err(TitanError::MalformedSaveData(_))));
+
+    let result = ecs.try_deserialize("not json");
+    assert!(matches!(result, Err(TitanError::MalformedSaveData(_))));
+
+    // Corrupted entity indices fail the load instead of allocating up to the index
+    #[component]
+    struct Age(u8);
+    let mut ecs = ECS::default();
+    ecs.register_component::<Age>();
+    ecs.spawn_bundle((Age(10),));
+    let corrupt = |serial: String| serial.replace("\"index\":0", "\"index\":1000000000000");
+    let result = ecs.try_deserialize(&corrupt(ecs.serialize()));
+    assert!(matches!(result, Err(TitanError::MalformedSaveData(_))));
+    let serial = corrupt(ecs.serialize_with::<ColumnarJson>());
+    let result = ecs.try_deserialize_with::<ColumnarJson>(&serial);
+    assert!(matches!(result, Err(TitanError::MalformedSaveData(_))));
+    let result = ecs.try_load_additive(&corrupt(ecs.serialize()), EntityCollisionPolicy::Remap);
+    assert!(matches!(result, Err(TitanError::MalformedSaveData(_))));
+    assert_eq!(ecs.query::<(&Age,)>().result_iter().count(), 1);
+}
+
+#[test]
+fn unknown_kinds() {
+    #[component]
+    struct Age(u8);
+
+    let mut ecs = ECS::default();
+    let result = ecs.try_register_archetype::<(Age,)>();
+    assert!(matches!(result, Err(TitanError::UnknownComponentKind(_))));
+
+    ecs.spawn_bundle((Age(10),));
+    let result = ecs.try_serialize();
+    assert!(matches!(result, Err(TitanError::UnknownBundleKind(_))));
+
+    let mut ecs_2 = ECS::default();
+    let result = ecs_2.try_deserialize("[{\"bundle_kind\": \"Age\", \"entity\": {}}]");
+    assert!(matches!(result, Err(TitanError::UnknownBundleKind(_))));
+}
+
+#[test]
+fn lock_conflict() {
+    #[component]
+    struct Age(u8);
+
+    let mut ecs = ECS::default();
+    let entity = ecs.spawn_bundle((Age(10),));
+
+    let result = ecs.try_query::<(&mut Age, &Age)>();
+    assert!(matches!(result, Err(TitanError::LockConflict(_))));
+
+    let _age = ecs.get_mut::<Age>(entity).unwrap();
+    let result = ecs.try_get::<Age>(entity);
+    assert!(matches!(result, Err(TitanError::LockConflict(_))));
+}
+
+#[test]
+fn duplicate_component_kind() {
+    #[component]
+    struct Age(u8);
+
+    let mut ecs = ECS::default();
+    let result = ecs.try_spawn_bundle((Age(10), Age(20)));
+    assert!(matches!(result, Err(TitanError::DuplicateComponentKind(_))));
+}
+
+#[test]
+fn component_kind_collision() {
+    mod physics {
+        use titan_macros::component;
+        #[component]
+        pub struct Position(pub f32);
+        #[component(name = "physics::Velocity")]
+        pub struct Velocity(pub f32);
+    }
+    mod ui {
+        use titan_macros::component;
+        #[component]
+        pub struct Position(pub u32);
+        #[component(name = "ui::Velocity")]
+        pub struct Velocity(pub u32);
+    }
+
+    let mut ecs = ECS::default();
+    ecs.register_component::<physics::Position>();
+    ecs.register_component::<physics::Position>();
+    let result = ecs.try_register_component::<ui::Position>();
+    assert!(matches!(result, Err(TitanError::ComponentKindCollision(_))));
+
+    // Named components do not collide
+    ecs.register_component::<physics::Velocity>();
+    ecs.register_component::<ui::Velocity>();
+    let entity = ecs.spawn_bundle((physics::Velocity(1.5), ui::Velocity(2)));
+    let ecs_serial = ecs.serialize();
+    assert!(ecs_serial.contains("\"physics::Velocity\":1.5"));
+    assert!(ecs_serial.contains("\"ui::Velocity\":2"));
+
+    let mut ecs_2 = ECS::default();
+    ecs_2.register_component::<physics::Velocity>();
+    ecs_2.register_component::<ui::Velocity>();
+    ecs_2.deserialize(&ecs_serial);
+    assert_eq!(ecs_2.get::<ui::Velocity>(entity).unwrap().0, 2);
+
+    // Unregistered components of colliding kinds are never mixed up
+    let mut ecs = ECS::default();
+    let entity = ecs.spawn_bundle((physics::Position(1.5),));
+    let result = ecs.try_spawn_bundle((ui::Position(2),));
+    assert!(matches!(result, Err(TitanError::ComponentKindCollision(_))));
+    assert!(matches!(
+        ecs.try_get::<ui::Position>(entity),
+        Err(TitanError::ComponentKindCollision(_))
+    ));
+    assert!(matches!(
+        ecs.try_query::<(&mut ui::Position,)>(),
+        Err(TitanError::ComponentKindCollision(_))
+    ));
+    let result = ecs.try_insert_component(entity, ui::Position(3));
+    assert!(matches!(result, Err(TitanError::ComponentKindCollision(_))));
+    let result = ecs.try_remove_component::<ui::Position>(entity);
+    assert!(matches!(result, Err(TitanError::ComponentKindCollision(_))));
+
+    ecs.spawn_bundle((physics::Position(2.5), physics::Velocity(1.0)));
+    let entity_2 = ecs.spawn_bundle((physics::Velocity(2.0),));
+    let result = ecs.try_insert_component(entity_2, ui::Position(4));
+    assert!(matches!(result, Err(TitanError::ComponentKindCollision(_))));
+    assert_eq!(ecs.get::<physics::Position>(entity).unwrap().0, 1.5);
+    assert_eq!(ecs.get::<physics::Velocity>(entity_2).unwrap().0, 2.0);
+}
@@ -0,0 +1,194 @@
+use titan::*;
+use titan_macros::component;
+
+#[test]
+fn query_entity() {
+    #[component]
+    #[derive(PartialEq)]
+    struct Age(u8);
+    #[component]
+    #[derive(PartialEq)]
+    struct Height(u8);
+
+    let mut ecs = ECS::default();
+    let entity_a = ecs.spawn_bundle((Age(10),));
+    let entity_b = ecs.spawn_bundle((Age(20), Height(200)));
+    let entity_c = ecs.spawn_bundle((Age(30), Height(250)));
+
+    let mut result = ecs.query::<(Entity, &Age)>();
+    let result: Vec<_> = result.result_iter().collect();
+    assert!(result.contains(&(entity_a, &Age(10))));
+    assert!(result.contains(&(entity_b, &Age(20))));
+    assert!(result.contains(&(entity_c, &Age(30))));
+    assert_eq!(result.len(), 3);
+
+    let mut result = ecs.query::<(&Height, Entity)>();
+    let result: Vec<_> = result.result_iter().collect();
+    assert!(result.contains(&(&Height(200), entity_b)));
+    assert!(result.contains(&(&Height(250), entity_c)));
+    assert_eq!(result.len(), 2);
+}
+
+#[test]
+fn query_optional() {
+    #[component]
+    #[derive(PartialEq)]
+    struct Age(u8);
+    #[component]
+    #[derive(PartialEq)]
+    struct Height(u8);
+
+    let mut ecs = ECS::default();
+    ecs.spawn_bundle((Age(10),));
+    ecs.spawn_bundle((Age(20), Height(200)));
+
+    {
+        let mut result = ecs.query::<(&Age, Option<&Height>)>();
+        let result: Vec<_> = result.result_iter().collect();
+        assert!(result.contains(&(&Age(10), None)));
+        assert!(result.contains(&(&Age(20), Some(&Height(200)))));
+        assert_eq!(result.len(), 2);
+    }
+
+    for (age, height) in ecs.query::<(&Age, Option<&mut Height>)>().result_iter() {
+        if let Some(mut height) = height {
+            height.0 += age.0;
+        }
+    }
+
+    let mut result = ecs.query::<(&Age, Option<&Height>)>();
+    let result: Vec<_> = result.result_iter().collect();
+    assert!(result.contains(&(&Age(10), None)));
+    assert!(result.contains(&(&Age(20), Some(&Height(220)))));
+}
+
+#[test]
+fn query_with_without() {
+    #[component]
+    #[derive(PartialEq)]
+    struct Enemy(u8);
+    #[component]
+    struct Stunned;
+    #[component]
+    struct Boss;
+
+    let mut ecs = ECS::default();
+    ecs.spawn_bundle((Enemy(1),));
+    ecs.spawn_bundle((Enemy(2), Stunned));
+    ecs.spawn_bundle((Enemy(3), Boss));
+    ecs.spawn_bundle((Enemy(4), Boss, Stunned));
+
+    let mut result = ecs.query::<(&Enemy, Without<Stunned>)>();
+    let result: Vec<_> = result.result_iter().map(|(enemy, _)| enemy).collect();
+    assert!(result.contains(&&Enemy(1)));
+    assert!(result.contains(&&Enemy(3)));
+    assert_eq!(result.len(), 2);
+
+    let mut result = ecs.query::<(&Enemy, With<Boss>, Without<Stunned>)>();
+    let result: Vec<_> = result.result_iter().map(|(enemy, _, _)| enemy).collect();
+    assert_eq!(result, vec![&Enemy(3)]);
+}
+
+#[test]
+fn query_filtered() {
+    #[component]
+    #[derive(PartialEq)]
+    struct Enemy(u8);
+    #[component]
+    struct Stunned;
+    #[component]
+    struct Boss;
+
+    let mut ecs = ECS::default();
+    ecs.spawn_bundle((Enemy(1),));
+    ecs.spawn_bundle((Enemy(2), Stunned));
+    let boss = ecs.spawn_bundle((Enemy(3), Boss));
+    ecs.spawn_bundle((Enemy(4), Boss, Stunned));
+
+    // Nothing is yielded for the filter, so single parameter queries yield bare components
+    {
+        let mut result = ecs.query_filtered::<(&Enemy,), (With<Boss>, Without<Stunned>)>();
+        let result: Vec<_> = result.result_iter().collect();
+        assert_eq!(result, vec![&Enemy(3)]);
+    }
+
+    let last_run_tick = ecs.increment_change_tick();
+    ecs.get_mut::<Enemy>(boss).unwrap().0 = 5;
+    let mut result = ecs.query_filtered_since::<(Entity,), (Changed<Enemy>,)>(last_run_tick);
+    let result: Vec<_> = result.result_iter().collect();
+    assert_eq!(result, vec![boss]);
+}
+
+#[test]
+fn query_added_changed() {
+    #[component]
+    struct Position(i32);
+    #[component]
+    struct Velocity(i32);
+
+    let mut ecs = ECS::default();
+    let moving = ecs.spawn_bundle((Position(0), Velocity(1)));
+    let still = ecs.spawn_bundle((Position(0),));
+
+    // Components count as added and changed until the tick ends
+    let result = ecs
+        .query::<(Entity, Added<Position>)>()
+        .result_iter()
+        .count();
+    assert_eq!(result, 2);
+    let last_run_tick = ecs.increment_change_tick();
+    assert_eq!(ecs.query::<(Added<Position>,)>().result_iter().count(), 0);
+    assert_eq!(ecs.query::<(Changed<Position>,)>().result_iter().count(), 0);
+
+    // Only components written through a write query are recorded as changed
+    for (entity, mut position) in ecs.query::<(Entity, &mut Position)>().result_iter() {
+        if entity == moving {
+            position.0 += 1;
+        } else {
+            assert_eq!(position.0, 0);
+        }
+    }
+    let mut result = ecs.query_since::<(Entity, Changed<Position>)>(last_run_tick);
+    let result: Vec<_> = result.result_iter().map(|(entity, _)| entity).collect();
+    assert_eq!(result, vec![moving]);
+    let last_run_tick = ecs.increment_change_tick();
+    for (mut position, velocity) in ecs.query::<(&mut Position, &Velocity)>().result_iter() {
+        position.0 += velocity.0;
+    }
+    let mut result = ecs.query_since::<(Entity, Changed<Position>)>(last_run_tick);
+    let result: Vec<_> = result.result_iter().map(|(entity, _)| entity).collect();
+    assert_eq!(result, vec![moving]);
+
+    // Inserted components and entities migrated between archetypes keep accurate ticks
+    let last_run_tick = ecs.increment_change_tick();
+    ecs.insert_component(still, Velocity(2));
+    let mut result = ecs.query_since::<(Entity, Added<Velocity>)>(last_run_tick);
+    let result: Vec<_> = result.result_iter().map(|(entity, _)| entity).collect();
+    assert_eq!(result, vec![still]);
+    let result = ecs
+        .query_since::<(Added<Position>,)>(last_run_tick)
+        .result_iter()
+        .count();
+    assert_eq!(result, 0);
+
+    // Write access through get_mut is recorded as a change
+    let last_run_tick = ecs.increment_change_tick();
+    ecs.get_mut::<Velocity>(moving).unwrap().0 = 3;
+    {
+        let mut result = ecs.query_since::<(Entity, &Velocity, Changed<Velocity>)>(last_run_tick);
+        let result: Vec<_> = result
+            .result_iter()
+            .map(|(entity, velocity, _)| (entity, velocity.0))
+            .collect();
+        assert_eq!(result, vec![(moving, 3)]);
+    }
+
+    // Reading through get_mut is not
+    let last_run_tick = ecs.increment_change_tick();
+    assert_eq!(ecs.get_mut::<Velocity>(moving).unwrap().0, 3);
+    let result = ecs
+        .query_since::<(Changed<Velocity>,)>(last_run_tick)
+        .result_iter()
+        .count();
+    assert_eq!(result, 0);
+}
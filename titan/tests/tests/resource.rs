@@ -0,0 +1,29 @@
+use titan::*;
+
+#[test]
+fn resources() {
+    #[derive(Debug, PartialEq)]
+    struct Clock(u32);
+
+    let mut ecs = ECS::default();
+    assert!(ecs.resource::<Clock>().is_none());
+    assert!(ecs.insert_resource(Clock(0)).is_none());
+    assert!(ecs.has_resource::<Clock>());
+
+    ecs.resource_mut::<Clock>().unwrap().0 += 1;
+    assert_eq!(*ecs.resource::<Clock>().unwrap(), Clock(1));
+
+    // Resources are locked like component vecs
+    {
+        let _clock = ecs.resource_mut::<Clock>().unwrap();
+        let result = ecs.try_resource::<Clock>();
+        assert!(matches!(result, Err(TitanError::ResourceLockConflict(_))));
+    }
+    let clock = ecs.resource::<Clock>().unwrap();
+    assert!(ecs.try_resource::<Clock>().is_ok());
+    drop(clock);
+
+    assert_eq!(ecs.insert_resource(Clock(5)), Some(Clock(1)));
+    assert_eq!(ecs.remove_resource::<Clock>(), Some(Clock(5)));
+    assert!(!ecs.has_resource::<Clock>());
+}
@@ -0,0 +1,220 @@
+use std::{
+    collections::HashSet,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, ThreadId},
+    time::{Duration, Instant},
+};
+use titan::*;
+use titan_macros::component;
+
+#[component]
+struct Position(i32);
+#[component]
+struct Velocity(i32);
+
+#[derive(Default)]
+struct Log(Vec<&'static str>);
+
+fn movement(mut query: SystemQuery<(&mut Position, &Velocity)>) {
+    for (mut position, velocity) in query.result_iter() {
+        position.0 += velocity.0;
+    }
+}
+
+fn count_moved(
+    mut query: SystemQuery<(Entity,), (Changed<Position>,)>,
+    mut moved: ResourceRefMut<usize>,
+) {
+    *moved += query.result_iter().count();
+}
+
+#[test]
+fn schedule_function_systems() {
+    let mut ecs = ECS::default();
+    ecs.insert_resource(0_usize);
+    let entity = ecs.spawn_bundle((Position(0), Velocity(2)));
+    ecs.spawn_bundle((Position(0),));
+
+    let mut schedule = Schedule::new();
+    schedule
+        .add_stage("update")
+        .add_system("update", movement)
+        .add_system("update", count_moved);
+
+    // Systems see the changes of systems which ran before them in the same run
+    schedule.run(&mut ecs);
+    assert_eq!(ecs.get::<Position>(entity).unwrap().0, 2);
+    assert_eq!(*ecs.resource::<usize>().unwrap(), 2);
+
+    schedule.run(&mut ecs);
+    assert_eq!(ecs.get::<Position>(entity).unwrap().0, 4);
+    assert_eq!(*ecs.resource::<usize>().unwrap(), 3);
+}
+
+#[test]
+fn schedule_order() {
+    fn system(log: &Arc<Mutex<Log>>, name: &'static str) -> impl FnMut() {
+        let log = log.clone();
+        move || log.lock().unwrap().0.push(name)
+    }
+
+    let log = Arc::new(Mutex::new(Log::default()));
+    let mut schedule = Schedule::new();
+    schedule.add_stage("first").add_stage("second");
+    schedule
+        .add_system("second", system(&log, "render"))
+        .add_system("first", system(&log, "physics").label("physics"))
+        .add_system("first", system(&log, "input").before("physics"))
+        .add_system("first", system(&log, "collisions").after("physics"));
+
+    let mut ecs = ECS::default();
+    schedule.run(&mut ecs);
+    assert_eq!(
+        log.lock().unwrap().0,
+        vec!["input", "physics", "collisions", "render"]
+    );
+}
+
+#[test]
+fn schedule_errors() {
+    let mut schedule = Schedule::new();
+    let result = schedule.try_add_system("update", movement);
+    assert!(matches!(result, Err(TitanError::UnknownStage(_))));
+
+    schedule
+        .add_stage("update")
+        .add_system("update", movement.label("a").after("b"))
+        .add_system("update", count_moved.label("b").after("a"));
+    let mut ecs = ECS::default();
+    let result = schedule.try_run(&mut ecs);
+    assert!(matches!(result, Err(TitanError::SystemOrderCycle(_))));
+
+    // Systems fail if a required resource has not been inserted
+    let mut schedule = Schedule::new();
+    schedule
+        .add_stage("update")
+        .add_system("update", count_moved);
+    let result = schedule.try_run(&mut ecs);
+    assert!(matches!(result, Err(TitanError::ResourceNotFound(_))));
+}
+
+#[test]
+fn schedule_parallel() {
+    fn accelerate(mut query: SystemQuery<(&mut Velocity,)>) {
+        for mut velocity in query.result_iter() {
+            velocity.0 += 1;
+        }
+    }
+
+    // Systems conflict if one writes what the other reads or writes
+    fn access<Params>(system: impl IntoSystem<Params>) -> Access {
+        system.into_system().access()
+    }
+    assert!(!access(movement).is_compatible(&access(accelerate)));
+    assert!(!access(movement).is_compatible(&access(count_moved)));
+    assert!(access(accelerate).is_compatible(&access(count_moved)));
+
+    let mut ecs = ECS::default();
+    ecs.insert_resource(0_usize);
+    let entity = ecs.spawn_bundle((Position(0), Velocity(1)));
+
+    let mut schedule = Schedule::new();
+    schedule
+        .set_execution_mode(ExecutionMode::Parallel(4))
+        .add_stage("update")
+        .add_system("update", accelerate)
+        .add_system("update", movement)
+        .add_system("update", count_moved);
+
+    // Conflicting systems still run in the order of the stage
+    schedule.run(&mut ecs);
+    assert_eq!(ecs.get::<Position>(entity).unwrap().0, 2);
+    assert_eq!(*ecs.resource::<usize>().unwrap(), 1);
+    schedule.run(&mut ecs);
+    assert_eq!(ecs.get::<Position>(entity).unwrap().0, 5);
+    assert_eq!(*ecs.resource::<usize>().unwrap(), 2);
+}
+
+#[test]
+fn schedule_parallel_threads() {
+    // Each system waits for the other to start, which only happens if they run at the same time
+    fn system(
+        started: &Arc<[AtomicBool; 2]>,
+        thread_ids: &Arc<Mutex<HashSet<ThreadId>>>,
+        index: usize,
+    ) -> impl FnMut() {
+        let started = started.clone();
+        let thread_ids = thread_ids.clone();
+        move || {
+            thread_ids.lock().unwrap().insert(thread::current().id());
+            started[index].store(true, Ordering::SeqCst);
+            let deadline = Instant::now() + Duration::from_secs(5);
+            while !started[1 - index].load(Ordering::SeqCst) {
+                assert!(
+                    Instant::now() < deadline,
+                    "Systems did not run at the same time"
+                );
+                thread::yield_now();
+            }
+        }
+    }
+
+    let started = Arc::new([AtomicBool::new(false), AtomicBool::new(false)]);
+    let thread_ids = Arc::new(Mutex::new(HashSet::new()));
+    let record_thread_id = {
+        let thread_ids = thread_ids.clone();
+        move || {
+            thread_ids.lock().unwrap().insert(thread::current().id());
+        }
+    };
+    let mut schedule = Schedule::new();
+    schedule
+        .set_execution_mode(ExecutionMode::Parallel(2))
+        .add_stage("update")
+        .add_system("update", system(&started, &thread_ids, 0))
+        .add_system("update", system(&started, &thread_ids, 1))
+        .add_system("update", record_thread_id);
+    let mut ecs = ECS::default();
+
+    // Systems of a run share the given number of threads
+    for _ in 0..4 {
+        schedule.run(&mut ecs);
+        assert_eq!(thread_ids.lock().unwrap().len(), 2);
+        thread_ids.lock().unwrap().clear();
+        for started in started.iter() {
+            started.store(false, Ordering::SeqCst);
+        }
+    }
+}
+
+#[test]
+fn schedule_parallel_panic() {
+    fn noop() {}
+
+    let panicked = Arc::new(AtomicBool::new(false));
+    let panic_once = {
+        let panicked = panicked.clone();
+        move || {
+            if !panicked.swap(true, Ordering::SeqCst) {
+                panic!("System failed");
+            }
+        }
+    };
+    let mut ecs = ECS::default();
+    let mut schedule = Schedule::new();
+    schedule
+        .set_execution_mode(ExecutionMode::Parallel(2))
+        .add_stage("update")
+        .add_system("update", noop)
+        .add_system("update", panic_once);
+
+    // The panic of the system is resumed on the calling thread
+    let result = panic::catch_unwind(AssertUnwindSafe(|| schedule.run(&mut ecs)));
+    let payload = result.unwrap_err();
+    assert_eq!(payload.downcast_ref::<&str>(), Some(&"System failed"));
+    schedule.run(&mut ecs);
+}
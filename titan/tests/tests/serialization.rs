@@ -0,0 +1,778 @@
+use titan::*;
+use titan_macros::component;
+
+#[test]
+fn automatic_archetype_registration() {
+    #[component]
+    #[derive(PartialEq)]
+    struct Age(u8);
+    #[component]
+    #[derive(PartialEq)]
+    struct Height(u8);
+
+    let mut ecs = ECS::default();
+    ecs.register_component::<Age>();
+    ecs.register_component::<Height>();
+    let entity_a = ecs.spawn_bundle((Age(10), Height(100)));
+    let entity_b = ecs.spawn_bundle((Age(20),));
+    ecs.insert_component(entity_b, Height(200));
+    ecs.remove_component::<Age>(entity_a);
+
+    let ecs_serial = ecs.serialize();
+    let mut ecs_2 = ECS::default();
+    ecs_2.register_component::<Age>();
+    ecs_2.register_component::<Height>();
+    ecs_2.deserialize(&ecs_serial);
+
+    assert!(ecs_2.get::<Age>(entity_a).is_none());
+    assert_eq!(*ecs_2.get::<Height>(entity_a).unwrap(), Height(100));
+    assert_eq!(*ecs_2.get::<Age>(entity_b).unwrap(), Age(20));
+    assert_eq!(*ecs_2.get::<Height>(entity_b).unwrap(), Height(200));
+
+    // Archetypes with unregistered components are registered once the components are
+    #[component]
+    struct Weight(u8);
+    let mut ecs = ECS::default();
+    ecs.register_component::<Age>();
+    ecs.spawn_bundle((Age(10), Weight(50)));
+    let entity = ecs.spawn_bundle((Age(20),));
+    ecs.insert_component(entity, Weight(60));
+    let result = ecs.try_serialize();
+    assert!(matches!(result, Err(TitanError::UnknownBundleKind(_))));
+
+    ecs.register_component::<Weight>();
+    ecs.spawn_bundle((Age(30), Weight(70)));
+    ecs.remove_component::<Weight>(entity);
+    ecs.insert_component(entity, Weight(60));
+    assert!(ecs.try_serialize().is_ok());
+}
+
+#[test]
+fn binary_round_trip() {
+    #[component]
+    #[derive(PartialEq)]
+    struct Name(String);
+    #[component]
+    #[derive(PartialEq)]
+    struct Score(u32);
+
+    let mut ecs = ECS::default();
+    ecs.register_component::<Name>();
+    ecs.register_component::<Score>();
+    let entity_a = ecs.spawn_bundle((Name("a".to_string()), Score(1)));
+    let entity_b = ecs.spawn_bundle((Score(2),));
+    let entity_c = ecs.spawn_bundle((Name("c".to_string()), Score(3)));
+    ecs.despawn(entity_a);
+
+    let ecs_serial = ecs.serialize_with::<Binary>();
+
+    let mut ecs_2 = ECS::default();
+    ecs_2.register_component::<Name>();
+    ecs_2.register_component::<Score>();
+    ecs_2.deserialize_with::<Binary>(&ecs_serial);
+
+    assert!(!ecs_2.is_alive(entity_a));
+    assert_eq!(*ecs_2.get::<Score>(entity_b).unwrap(), Score(2));
+    assert_eq!(*ecs_2.get::<Name>(entity_c).unwrap(), Name("c".to_string()));
+    assert_eq!(*ecs_2.get::<Score>(entity_c).unwrap(), Score(3));
+
+    // Restored entities can be despawned and new entities reuse free indices
+    assert!(ecs_2.despawn(entity_b));
+    let entity_d = ecs_2.spawn_bundle((Score(4),));
+    assert_eq!(*ecs_2.get::<Score>(entity_d).unwrap(), Score(4));
+    assert_eq!(*ecs_2.get::<Score>(entity_c).unwrap(), Score(3));
+}
+
+#[test]
+fn columnar_json_round_trip() {
+    #[component]
+    #[derive(PartialEq)]
+    struct Level(u8);
+    #[component]
+    #[derive(PartialEq)]
+    struct Health(u8);
+
+    let mut ecs = ECS::default();
+    ecs.register_component::<Level>();
+    ecs.register_component::<Health>();
+    let entities: Vec<_> = (0..10)
+        .map(|i| ecs.spawn_bundle((Level(i), Health(100 - i))))
+        .collect();
+    let entity_level_only = ecs.spawn_bundle((Level(50),));
+    ecs.despawn(entities[3]);
+
+    let ecs_serial = ecs.serialize_with::<ColumnarJson>();
+    assert!(ecs_serial.len() < ecs.serialize().len());
+
+    // One record per archetype with one array per component column
+    let save: serde_json::Value = serde_json::from_str(&ecs_serial).unwrap();
+    let records = save["archetypes"].as_array().unwrap();
+    assert_eq!(records.len(), 2);
+    for record in records {
+        let entity_count = record["entity_ids"].as_array().unwrap().len();
+        for column in record["columns"].as_object().unwrap().values() {
+            assert_eq!(column.as_array().unwrap().len(), entity_count);
+        }
+    }
+
+    let mut ecs_2 = ECS::default();
+    ecs_2.register_component::<Level>();
+    ecs_2.register_component::<Health>();
+    ecs_2.deserialize_with::<ColumnarJson>(&ecs_serial);
+
+    assert!(!ecs_2.is_alive(entities[3]));
+    for (i, entity) in entities.iter().enumerate().filter(|(i, _)| *i != 3) {
+        assert_eq!(*ecs_2.get::<Level>(*entity).unwrap(), Level(i as u8));
+        assert_eq!(
+            *ecs_2.get::<Health>(*entity).unwrap(),
+            Health(100 - i as u8)
+        );
+    }
+    assert_eq!(*ecs_2.get::<Level>(entity_level_only).unwrap(), Level(50));
+    assert!(ecs_2.get::<Health>(entity_level_only).is_none());
+}
+
+#[test]
+fn streaming_round_trip() {
+    #[component]
+    #[derive(PartialEq)]
+    struct Mass(u32);
+    #[component]
+    #[derive(PartialEq)]
+    struct Charge(i8);
+
+    let mut ecs = ECS::default();
+    ecs.register_component::<Mass>();
+    ecs.register_component::<Charge>();
+    let entity_a = ecs.spawn_bundle((Mass(1), Charge(-1)));
+    let entity_b = ecs.spawn_bundle((Mass(2),));
+
+    let mut ecs_serial = Vec::new();
+    ecs.serialize_to(&mut ecs_serial);
+    assert_eq!(ecs_serial, ecs.serialize().into_bytes());
+
+    let mut ecs_2 = ECS::default();
+    ecs_2.register_component::<Mass>();
+    ecs_2.register_component::<Charge>();
+    ecs_2.deserialize_from(ecs_serial.as_slice());
+
+    assert_eq!(*ecs_2.get::<Mass>(entity_a).unwrap(), Mass(1));
+    assert_eq!(*ecs_2.get::<Charge>(entity_a).unwrap(), Charge(-1));
+    assert_eq!(*ecs_2.get::<Mass>(entity_b).unwrap(), Mass(2));
+
+    // Errors raised while restoring an entity are returned as is
+    let mut ecs_3 = ECS::default();
+    ecs_3.register_component::<Mass>();
+    let result = ecs_3.try_deserialize_from(ecs_serial.as_slice());
+    assert!(matches!(result, Err(TitanError::UnknownBundleKind(_))));
+}
+
+#[test]
+fn load_additive() {
+    #[component]
+    #[derive(PartialEq)]
+    struct Tile(u32);
+
+    let mut chunk = ECS::default();
+    chunk.register_component::<Tile>();
+    let chunk_entity_a = chunk.spawn_bundle((Tile(1),));
+    chunk.despawn(chunk_entity_a);
+    let chunk_entity_b = chunk.spawn_bundle((Tile(2),));
+    let chunk_serial = chunk.serialize();
+
+    let mut world = ECS::default();
+    world.register_component::<Tile>();
+    let world_entity_a = world.spawn_bundle((Tile(10),));
+    let world_entity_b = world.spawn_bundle((Tile(20),));
+
+    // Error leaves the world untouched
+    let result = world.try_load_additive(&chunk_serial, EntityCollisionPolicy::Error);
+    assert!(matches!(result, Err(TitanError::EntityCollision(_))));
+    assert_eq!(world.query::<(&Tile,)>().result_iter().count(), 2);
+
+    // Remap gives colliding entities fresh handles
+    let entity_map = world.load_additive(&chunk_serial, EntityCollisionPolicy::Remap);
+    let remapped_entity = entity_map[&chunk_entity_b];
+    assert_ne!(remapped_entity, chunk_entity_b);
+    assert_eq!(*world.get::<Tile>(remapped_entity).unwrap(), Tile(2));
+    assert_eq!(*world.get::<Tile>(world_entity_a).unwrap(), Tile(10));
+    assert_eq!(world.query::<(&Tile,)>().result_iter().count(), 3);
+
+    // Overwrite despawns the living entity and keeps the loaded handle
+    let entity_map = world.load_additive(&chunk_serial, EntityCollisionPolicy::Overwrite);
+    assert_eq!(entity_map[&chunk_entity_b], chunk_entity_b);
+    assert!(!world.is_alive(world_entity_a));
+    assert_eq!(*world.get::<Tile>(chunk_entity_b).unwrap(), Tile(2));
+    assert_eq!(*world.get::<Tile>(world_entity_b).unwrap(), Tile(20));
+    assert_eq!(*world.get::<Tile>(remapped_entity).unwrap(), Tile(2));
+    assert_eq!(world.query::<(&Tile,)>().result_iter().count(), 3);
+}
+
+#[test]
+fn load_additive_stale_handles() {
+    #[component]
+    #[derive(PartialEq)]
+    struct Tile(u32);
+
+    let mut world = ECS::default();
+    world.register_component::<Tile>();
+    let stale_entity = world.spawn_bundle((Tile(1),));
+    let chunk_serial = world.serialize();
+    world.despawn(stale_entity);
+
+    // Loading a despawned entity back does not revive handles to it
+    let entity_map = world.load_additive(&chunk_serial, EntityCollisionPolicy::Remap);
+    let loaded_entity = entity_map[&stale_entity];
+    assert_ne!(loaded_entity, stale_entity);
+    assert!(!world.is_alive(stale_entity));
+    assert!(world.get::<Tile>(stale_entity).is_none());
+    assert_eq!(*world.get::<Tile>(loaded_entity).unwrap(), Tile(1));
+
+    // Overwrite does not roll back the generation of the despawned living entity
+    let entity_map = world.load_additive(&chunk_serial, EntityCollisionPolicy::Overwrite);
+    let overwriting_entity = entity_map[&stale_entity];
+    assert_eq!(overwriting_entity.index(), loaded_entity.index());
+    assert!(overwriting_entity.generation() > loaded_entity.generation());
+    assert!(!world.is_alive(loaded_entity));
+    assert!(!world.is_alive(stale_entity));
+    assert_eq!(*world.get::<Tile>(overwriting_entity).unwrap(), Tile(1));
+    assert_eq!(world.query::<(&Tile,)>().result_iter().count(), 1);
+}
+
+#[test]
+fn round_trip_stale_handles() {
+    #[component]
+    struct Tile(u32);
+    #[component]
+    struct Scratch(u32);
+
+    let mut ecs = ECS::default();
+    ecs.register_component::<Tile>();
+    ecs.register_component::<Scratch>();
+    let despawned = ecs.spawn_bundle((Tile(1),));
+    let kept = ecs.spawn_bundle((Tile(2),));
+    let unsaved = ecs.spawn_bundle((Scratch(3),));
+    ecs.despawn(despawned);
+    let options = SerializeOptions::new().with_component::<Tile>();
+    let serial = ecs.serialize_filtered(&options);
+    let columnar_serial = ecs.serialize_filtered_with::<ColumnarJson>(&options);
+    let binary_serial = ecs.serialize_filtered_with::<Binary>(&options);
+
+    // Handles of despawned and unsaved entities stay stale, and their indices are reused first
+    let load = |ecs_2: &mut ECS| {
+        assert!(ecs_2.is_alive(kept));
+        assert!(!ecs_2.is_alive(despawned));
+        assert!(!ecs_2.is_alive(unsaved));
+        let spawned = [
+            ecs_2.spawn_bundle((Tile(4),)),
+            ecs_2.spawn_bundle((Tile(5),)),
+        ];
+        assert_eq!(spawned[0].index(), unsaved.index());
+        assert_eq!(spawned[1].index(), despawned.index());
+        assert!(!spawned.contains(&despawned) && !spawned.contains(&unsaved));
+        assert!(!ecs_2.is_alive(despawned));
+        assert!(!ecs_2.is_alive(unsaved));
+    };
+    let new_ecs = || {
+        let mut ecs_2 = ECS::default();
+        ecs_2.register_component::<Tile>();
+        ecs_2
+    };
+    let mut ecs_2 = new_ecs();
+    ecs_2.deserialize(&serial);
+    load(&mut ecs_2);
+    let mut ecs_2 = new_ecs();
+    ecs_2.deserialize_with::<ColumnarJson>(&columnar_serial);
+    load(&mut ecs_2);
+    let mut ecs_2 = new_ecs();
+    ecs_2.deserialize_with::<Binary>(&binary_serial);
+    load(&mut ecs_2);
+
+    // Loading additively into an empty world keeps handles stale as well
+    let mut ecs_2 = new_ecs();
+    ecs_2.load_additive(&serial, EntityCollisionPolicy::Error);
+    load(&mut ecs_2);
+}
+
+#[test]
+fn sparse_entity_indices() {
+    #[component]
+    struct Age(u8);
+
+    // Indices far beyond the number of saved entities are valid if within the saved slots
+    let mut ecs = ECS::default();
+    ecs.register_component::<Age>();
+    let entities: Vec<Entity> = (0..(1 << 20) + 10)
+        .map(|_| ecs.spawn_bundle((Age(1),)))
+        .collect();
+    let last_entity = *entities.last().unwrap();
+    for entity in entities.iter().filter(|entity| **entity != last_entity) {
+        ecs.despawn(*entity);
+    }
+    let mut ecs_2 = ECS::default();
+    ecs_2.register_component::<Age>();
+    ecs_2.deserialize(&ecs.serialize());
+    assert!(ecs_2.is_alive(last_entity));
+    let mut serial = Vec::new();
+    ecs.serialize_to(&mut serial);
+    ecs_2.deserialize_from(&serial[..]);
+    assert!(ecs_2.is_alive(last_entity));
+    ecs_2.deserialize_with::<Binary>(&ecs.serialize_with::<Binary>());
+    assert!(ecs_2.is_alive(last_entity));
+}
+
+#[test]
+fn map_entities_on_load() {
+    #[component]
+    #[derive(PartialEq)]
+    struct Parent(#[entity] Option<Entity>);
+    #[component]
+    #[derive(PartialEq)]
+    struct Squad {
+        name: String,
+        #[entity]
+        leader: Option<Entity>,
+        #[entity]
+        members: Vec<Entity>,
+    }
+
+    let mut prefab = ECS::default();
+    prefab.register_component::<Parent>();
+    prefab.register_component::<Squad>();
+    let leader = prefab.spawn_bundle((Parent(None),));
+    let member = prefab.spawn_bundle((Parent(Some(leader)),));
+    let squad = prefab.spawn_bundle((Squad {
+        name: "alpha".to_string(),
+        leader: Some(leader),
+        members: vec![leader, member],
+    },));
+    let prefab_serial = prefab.serialize();
+
+    let mut world = ECS::default();
+    world.register_component::<Parent>();
+    world.register_component::<Squad>();
+    let outsider = world.spawn_bundle((Parent(None),));
+    world.spawn_bundle((Parent(Some(outsider)),));
+    let entity_map = world.load_additive(&prefab_serial, EntityCollisionPolicy::Remap);
+
+    let new_leader = entity_map[&leader];
+    let new_member = entity_map[&member];
+    assert_eq!(
+        *world.get::<Parent>(new_member).unwrap(),
+        Parent(Some(new_leader))
+    );
+    assert_eq!(
+        *world.get::<Squad>(entity_map[&squad]).unwrap(),
+        Squad {
+            name: "alpha".to_string(),
+            leader: Some(new_leader),
+            members: vec![new_leader, new_member],
+        }
+    );
+    // References held by entities already in the world are left alone
+    let mut result = world.query::<(&Parent,)>();
+    assert!(result
+        .result_iter()
+        .any(|parent| *parent == Parent(Some(outsider))));
+}
+
+#[test]
+fn save_version_migrations() {
+    mod v0 {
+        use titan_macros::component;
+        #[component]
+        pub struct Stats {
+            pub hp: u32,
+        }
+    }
+    mod v2 {
+        use titan_macros::component;
+        #[component]
+        #[derive(PartialEq)]
+        pub struct Stats {
+            pub health: u32,
+            pub armor: u32,
+        }
+    }
+    fn rename_hp(mut value: serde_json::Value) -> serde_json::Value {
+        let hp = value["hp"].take();
+        value["health"] = hp;
+        value.as_object_mut().unwrap().remove("hp");
+        value
+    }
+    fn add_armor(mut value: serde_json::Value) -> serde_json::Value {
+        value["armor"] = serde_json::Value::from(5);
+        value
+    }
+
+    let mut old_ecs = ECS::default();
+    old_ecs.register_component::<v0::Stats>();
+    let entity = old_ecs.spawn_bundle((v0::Stats { hp: 30 },));
+    let old_serial = old_ecs.serialize();
+    let old_columnar_serial = old_ecs.serialize_with::<ColumnarJson>();
+    let old_binary_serial = old_ecs.serialize_with::<Binary>();
+
+    let mut ecs = ECS::default();
+    ecs.set_save_version(2);
+    ecs.register_component::<v2::Stats>();
+    ecs.register_migration::<v2::Stats>(1, add_armor);
+    ecs.register_migration::<v2::Stats>(0, rename_hp);
+    let expected = v2::Stats {
+        health: 30,
+        armor: 5,
+    };
+
+    ecs.deserialize(&old_serial);
+    assert_eq!(*ecs.get::<v2::Stats>(entity).unwrap(), expected);
+    ecs.deserialize_with::<ColumnarJson>(&old_columnar_serial);
+    assert_eq!(*ecs.get::<v2::Stats>(entity).unwrap(), expected);
+    let result = ecs.try_deserialize_with::<Binary>(&old_binary_serial);
+    assert!(matches!(result, Err(TitanError::MalformedSaveData(_))));
+
+    // A bare array of entities is loaded as a save of version 0
+    let legacy_serial = format!(
+        "[{{\"bundle_kind\":\"Stats\",\"entity\":{},\"Stats\":{{\"hp\":30}}}}]",
+        serde_json::to_string(&entity).unwrap()
+    );
+    ecs.deserialize(&legacy_serial);
+    assert_eq!(*ecs.get::<v2::Stats>(entity).unwrap(), expected);
+
+    // Entities may precede the version header
+    let reordered_serial = format!("{{\"entities\":{},\"version\":0}}", legacy_serial);
+    ecs.deserialize(&reordered_serial);
+    assert_eq!(*ecs.get::<v2::Stats>(entity).unwrap(), expected);
+
+    // Saves of the current version are not migrated, newer saves are rejected
+    let serial = ecs.serialize();
+    ecs.deserialize(&serial);
+    assert_eq!(*ecs.get::<v2::Stats>(entity).unwrap(), expected);
+    let mut old_ecs = ECS::default();
+    old_ecs.register_component::<v0::Stats>();
+    let result = old_ecs.try_deserialize(&serial);
+    assert!(matches!(result, Err(TitanError::UnsupportedSaveVersion(2))));
+}
+
+#[test]
+fn tolerate_unknown_components() {
+    #[component]
+    #[derive(PartialEq)]
+    struct Position(i32);
+    #[component]
+    #[derive(PartialEq)]
+    struct ModWeapon(String);
+
+    let mut modded = ECS::default();
+    modded.register_component::<Position>();
+    modded.register_component::<ModWeapon>();
+    let armed = modded.spawn_bundle((Position(1), ModWeapon("laser".to_string())));
+    let unarmed = modded.spawn_bundle((Position(2),));
+    let modded_serial = modded.serialize();
+    let modded_columnar_serial = modded.serialize_with::<ColumnarJson>();
+
+    let mut ecs = ECS::default();
+    ecs.register_component::<Position>();
+    let result = ecs.try_deserialize(&modded_serial);
+    assert!(matches!(result, Err(TitanError::UnknownBundleKind(_))));
+
+    // Drop places entities in the archetype of their remaining components
+    ecs.set_unknown_component_policy(UnknownComponentPolicy::Drop);
+    ecs.deserialize(&modded_serial);
+    assert_eq!(*ecs.get::<Position>(armed).unwrap(), Position(1));
+    assert_eq!(*ecs.get::<Position>(unarmed).unwrap(), Position(2));
+    assert!(!ecs.serialize().contains("laser"));
+    ecs.deserialize_with::<ColumnarJson>(&modded_columnar_serial);
+    assert_eq!(*ecs.get::<Position>(armed).unwrap(), Position(1));
+    assert_eq!(ecs.query::<(&Position,)>().result_iter().count(), 2);
+
+    // Preserve writes unknown components back on the next save
+    ecs.set_unknown_component_policy(UnknownComponentPolicy::Preserve);
+    ecs.deserialize(&modded_serial);
+    assert_eq!(*ecs.get::<Position>(armed).unwrap(), Position(1));
+    ecs.insert_component(armed, Position(3));
+    let ecs_serial = ecs.serialize();
+    let result = ecs.try_serialize_with::<Binary>();
+    assert!(matches!(result, Err(TitanError::SerializationFailed(_))));
+
+    modded.deserialize(&ecs_serial);
+    assert_eq!(*modded.get::<Position>(armed).unwrap(), Position(3));
+    assert_eq!(
+        *modded.get::<ModWeapon>(armed).unwrap(),
+        ModWeapon("laser".to_string())
+    );
+    assert!(modded.get::<ModWeapon>(unarmed).is_none());
+}
+
+#[test]
+fn partial_serialization() {
+    #[component]
+    struct Persistent;
+    #[component]
+    #[derive(PartialEq)]
+    struct Position(i32);
+    #[component]
+    struct RenderCache(Vec<u8>);
+
+    let mut ecs = ECS::default();
+    ecs.register_component::<Persistent>();
+    ecs.register_component::<Position>();
+    let saved = ecs.spawn_bundle((Persistent, Position(1), RenderCache(vec![0; 4])));
+    let not_saved = ecs.spawn_bundle((Position(2), RenderCache(vec![0; 4])));
+
+    // Unregistered components are fine as long as they are not selected
+    let result = ecs.try_serialize();
+    assert!(matches!(result, Err(TitanError::UnknownBundleKind(_))));
+    let options = SerializeOptions::new()
+        .with_query::<(With<Persistent>,)>()
+        .with_component::<Persistent>()
+        .with_component::<Position>();
+    let ecs_serial = ecs.serialize_filtered(&options);
+    let ecs_binary_serial = ecs.serialize_filtered_with::<Binary>(&options);
+
+    let mut ecs_2 = ECS::default();
+    ecs_2.register_component::<Persistent>();
+    ecs_2.register_component::<Position>();
+    ecs_2.deserialize(&ecs_serial);
+    assert_eq!(*ecs_2.get::<Position>(saved).unwrap(), Position(1));
+    assert!(ecs_2.get::<Persistent>(saved).is_some());
+    assert!(!ecs_2.is_alive(not_saved));
+
+    ecs_2.deserialize_with::<Binary>(&ecs_binary_serial);
+    assert_eq!(*ecs_2.get::<Position>(saved).unwrap(), Position(1));
+    assert!(!ecs_2.is_alive(not_saved));
+
+    // Filters combine `With` and `Without`
+    let options = SerializeOptions::new()
+        .with_query::<(With<Position>, Without<Persistent>)>()
+        .with_component::<Position>();
+    ecs_2.deserialize(&ecs.serialize_filtered(&options));
+    assert_eq!(*ecs_2.get::<Position>(not_saved).unwrap(), Position(2));
+    assert!(!ecs_2.is_alive(saved));
+
+    // Entities without any selected component are not saved
+    let options = SerializeOptions::new().with_component::<Persistent>();
+    ecs_2.deserialize(&ecs.serialize_filtered(&options));
+    assert!(ecs_2.get::<Persistent>(saved).is_some());
+    assert!(ecs_2.get::<Position>(saved).is_none());
+    assert!(!ecs_2.is_alive(not_saved));
+}
+
+#[test]
+fn transient_components() {
+    #[derive(Debug)]
+    struct TextureHandle(u32);
+    #[component(transient)]
+    struct RenderCache(TextureHandle);
+    #[component]
+    #[derive(PartialEq)]
+    struct Sprite {
+        path: String,
+        #[serde(skip)]
+        loaded: bool,
+        #[serde(default = "default_scale")]
+        scale: u8,
+    }
+    fn default_scale() -> u8 {
+        1
+    }
+
+    let mut ecs = ECS::default();
+    ecs.register_component::<Sprite>();
+    let sprite = Sprite {
+        path: "a.png".to_string(),
+        loaded: true,
+        scale: 2,
+    };
+    let entity_a = ecs.spawn_bundle((sprite, RenderCache(TextureHandle(7))));
+    let entity_b = ecs.spawn_bundle((RenderCache(TextureHandle(8)),));
+    assert_eq!(ecs.get::<RenderCache>(entity_a).unwrap().0 .0, 7);
+    ecs.insert_component(
+        entity_a,
+        Sprite {
+            path: "b.png".to_string(),
+            loaded: true,
+            scale: 3,
+        },
+    );
+
+    let ecs_serial = ecs.serialize();
+    assert!(!ecs_serial.contains("RenderCache"));
+    let mut ecs_2 = ECS::default();
+    ecs_2.register_component::<Sprite>();
+    ecs_2.deserialize(&ecs_serial);
+    let expected = Sprite {
+        path: "b.png".to_string(),
+        loaded: false,
+        scale: 3,
+    };
+    assert_eq!(*ecs_2.get::<Sprite>(entity_a).unwrap(), expected);
+    assert!(!ecs_2.is_alive(entity_b));
+
+    // Defaults of fields missing from the save are used
+    let ecs_serial = ecs_serial.replace(",\"scale\":3", "");
+    ecs_2.deserialize(&ecs_serial);
+    assert_eq!(ecs_2.get::<Sprite>(entity_a).unwrap().scale, 1);
+
+    // Archetypes with transient components can be registered before anything is spawned
+    let mut ecs_3 = ECS::default();
+    ecs_3.register_component::<Sprite>();
+    ecs_3.register_archetype::<(Sprite, RenderCache)>();
+    ecs_3.deserialize(&ecs_serial);
+    assert_eq!(ecs_3.get::<Sprite>(entity_a).unwrap().scale, 1);
+}
+
+#[test]
+fn canonical_output() {
+    #[component]
+    struct Position(i32);
+    #[component]
+    struct Velocity(i32);
+
+    let spawn_world = || {
+        let mut ecs = ECS::default();
+        ecs.register_component::<Position>();
+        ecs.register_component::<Velocity>();
+        let entities: Vec<Entity> = (0..6)
+            .map(|i| match i % 3 {
+                0 => ecs.spawn_bundle((Position(i),)),
+                1 => ecs.spawn_bundle((Velocity(i),)),
+                _ => ecs.spawn_bundle((Position(i), Velocity(i))),
+            })
+            .collect();
+        (ecs, entities)
+    };
+    let (ecs, _) = spawn_world();
+    let (mut ecs_2, entities) = spawn_world();
+
+    // Move the first entity to the end of its archetype so rows are in a different order
+    ecs_2.insert_component(entities[0], Velocity(0));
+    ecs_2.remove_component::<Velocity>(entities[0]);
+
+    let options = SerializeOptions::new().canonical();
+    assert_eq!(
+        ecs.serialize_filtered(&options),
+        ecs_2.serialize_filtered(&options)
+    );
+    assert_eq!(
+        ecs.serialize_filtered_with::<ColumnarJson>(&options),
+        ecs_2.serialize_filtered_with::<ColumnarJson>(&options)
+    );
+    assert_eq!(
+        ecs.serialize_filtered_with::<Binary>(&options),
+        ecs_2.serialize_filtered_with::<Binary>(&options)
+    );
+
+    // Canonical output still loads
+    let mut ecs_3 = ECS::default();
+    ecs_3.register_component::<Position>();
+    ecs_3.register_component::<Velocity>();
+    ecs_3.deserialize(&ecs_2.serialize_filtered(&options));
+    assert_eq!(ecs_3.get::<Position>(entities[0]).unwrap().0, 0);
+    assert!(ecs_3.get::<Velocity>(entities[0]).is_none());
+    assert_eq!(ecs_3.get::<Velocity>(entities[5]).unwrap().0, 5);
+}
+
+#[test]
+fn resource_serialization() {
+    #[component]
+    struct Position(i32);
+    #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    struct Clock(u32);
+    #[derive(Debug, PartialEq)]
+    struct Rng(u64);
+
+    let mut ecs = ECS::default();
+    ecs.register_component::<Position>();
+    ecs.register_resource::<Clock>("Clock");
+    ecs.spawn_bundle((Position(1),));
+    ecs.insert_resource(Clock(42));
+    ecs.insert_resource(Rng(7));
+    let ecs_serial = ecs.serialize();
+    let ecs_columnar_serial = ecs.serialize_with::<ColumnarJson>();
+    let ecs_binary_serial = ecs.serialize_with::<Binary>();
+    let save: serde_json::Value = serde_json::from_str(&ecs_serial).unwrap();
+    assert_eq!(save["resources"]["Clock"], serde_json::json!(42));
+
+    // Loading replaces saved resources and keeps resources which were not saved
+    let mut ecs_2 = ECS::default();
+    ecs_2.register_component::<Position>();
+    ecs_2.register_resource::<Clock>("Clock");
+    ecs_2.insert_resource(Rng(3));
+    ecs_2.deserialize(&ecs_serial);
+    assert_eq!(*ecs_2.resource::<Clock>().unwrap(), Clock(42));
+    assert_eq!(*ecs_2.resource::<Rng>().unwrap(), Rng(3));
+
+    ecs_2.insert_resource(Clock(0));
+    ecs_2.deserialize_with::<ColumnarJson>(&ecs_columnar_serial);
+    assert_eq!(*ecs_2.resource::<Clock>().unwrap(), Clock(42));
+    ecs_2.insert_resource(Clock(0));
+    ecs_2.deserialize_with::<Binary>(&ecs_binary_serial);
+    assert_eq!(*ecs_2.resource::<Clock>().unwrap(), Clock(42));
+    assert_eq!(ecs_2.query::<(&Position,)>().result_iter().count(), 1);
+
+    // Binary saves written before resources were saved end after the archetypes
+    #[derive(serde::Serialize)]
+    struct OldArchetypeRecord {
+        bundle_kind: String,
+        entity_ids: Vec<Entity>,
+        columns: std::collections::BTreeMap<String, Vec<u8>>,
+    }
+    #[derive(serde::Serialize)]
+    struct OldSave {
+        version: u32,
+        archetypes: Vec<OldArchetypeRecord>,
+    }
+    let mut old_ecs = ECS::default();
+    let entity = old_ecs.spawn_bundle((Position(2),));
+    let old_save = OldSave {
+        version: 0,
+        archetypes: vec![OldArchetypeRecord {
+            bundle_kind: "Position".to_string(),
+            entity_ids: vec![entity],
+            columns: [(
+                "Position".to_string(),
+                bincode::serialize(&vec![Position(2)]).unwrap(),
+            )]
+            .into(),
+        }],
+    };
+    let old_binary_serial = bincode::serialize(&old_save).unwrap();
+    ecs_2.deserialize_with::<Binary>(&old_binary_serial);
+    assert_eq!(*ecs_2.resource::<Clock>().unwrap(), Clock(42));
+    assert_eq!(
+        ecs_2
+            .query::<(&Position,)>()
+            .result_iter()
+            .next()
+            .unwrap()
+            .0,
+        2
+    );
+
+    // Unknown resources follow the unknown component policy
+    let mut ecs_3 = ECS::default();
+    ecs_3.register_component::<Position>();
+    let result = ecs_3.try_deserialize(&ecs_serial);
+    assert!(matches!(result, Err(TitanError::UnknownResourceKind(_))));
+    ecs_3.set_unknown_component_policy(UnknownComponentPolicy::Drop);
+    ecs_3.deserialize(&ecs_serial);
+    assert!(!ecs_3.has_resource::<Clock>());
+
+    // Filtered saves only include resources when asked to
+    let options = SerializeOptions::new().with_component::<Position>();
+    let save: serde_json::Value = serde_json::from_str(&ecs.serialize_filtered(&options)).unwrap();
+    assert!(save.get("resources").is_none());
+    let options = options.with_resources();
+    let save: serde_json::Value = serde_json::from_str(&ecs.serialize_filtered(&options)).unwrap();
+    assert_eq!(save["resources"]["Clock"], serde_json::json!(42));
+
+    // Loading additively keeps existing resources
+    let mut ecs_4 = ECS::default();
+    ecs_4.register_component::<Position>();
+    ecs_4.register_resource::<Clock>("Clock");
+    ecs_4.insert_resource(Clock(0));
+    ecs_4.load_additive(&ecs_serial, EntityCollisionPolicy::Remap);
+    assert_eq!(*ecs_4.resource::<Clock>().unwrap(), Clock(0));
+    ecs_4.remove_resource::<Clock>();
+    ecs_4.load_additive(&ecs_serial, EntityCollisionPolicy::Remap);
+    assert_eq!(*ecs_4.resource::<Clock>().unwrap(), Clock(42));
+}